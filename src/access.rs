@@ -0,0 +1,271 @@
+use std::{borrow::Cow, time::SystemTime};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::records::{ParseFailure, RecordFields, RecordParser, RecordTime};
+
+/// One request of a web server access log, as parsed by [`CommonLog`] or
+/// [`CombinedLog`]. Fields logged as `-` are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogEntry {
+    pub host: String,
+    pub ident: Option<String>,
+    pub user: Option<String>,
+    pub timestamp: DateTime<FixedOffset>,
+    /// The request line as logged, e.g. `GET /index.html HTTP/1.1`.
+    pub request: String,
+    /// Split from the request line, when it has the usual three parts.
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub protocol: Option<String>,
+    pub status: u16,
+    pub bytes: Option<u64>,
+    /// Only logged in the combined format.
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl RecordTime for AccessLogEntry {
+    fn record_time(&self) -> Option<SystemTime> {
+        Some(self.timestamp.into())
+    }
+}
+
+/// Fields are named after the struct members, with the timestamp as RFC 3339.
+impl RecordFields for AccessLogEntry {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        match name {
+            "host" => Some(Cow::Borrowed(&self.host)),
+            "ident" => self.ident.as_deref().map(Cow::Borrowed),
+            "user" => self.user.as_deref().map(Cow::Borrowed),
+            "timestamp" => Some(Cow::Owned(self.timestamp.to_rfc3339())),
+            "request" => Some(Cow::Borrowed(&self.request)),
+            "method" => self.method.as_deref().map(Cow::Borrowed),
+            "path" => self.path.as_deref().map(Cow::Borrowed),
+            "protocol" => self.protocol.as_deref().map(Cow::Borrowed),
+            "status" => Some(Cow::Owned(self.status.to_string())),
+            "bytes" => self.bytes.map(|bytes| Cow::Owned(bytes.to_string())),
+            "referer" => self.referer.as_deref().map(Cow::Borrowed),
+            "user_agent" => self.user_agent.as_deref().map(Cow::Borrowed),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the Common Log Format:
+/// `host ident user [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 2326`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommonLog;
+
+/// Parses the Combined Log Format, the Common Log Format followed by the quoted
+/// referer and user agent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CombinedLog;
+
+impl RecordParser for CommonLog {
+    type Record = AccessLogEntry;
+
+    fn parse(&self, line: &[u8]) -> Result<AccessLogEntry, ParseFailure> {
+        let mut tokens = Tokens::new(std::str::from_utf8(line)?);
+        let entry = parse_common(&mut tokens)?;
+        tokens.end()?;
+        Ok(entry)
+    }
+}
+
+impl RecordParser for CombinedLog {
+    type Record = AccessLogEntry;
+
+    fn parse(&self, line: &[u8]) -> Result<AccessLogEntry, ParseFailure> {
+        let mut tokens = Tokens::new(std::str::from_utf8(line)?);
+        let mut entry = parse_common(&mut tokens)?;
+        entry.referer = optional(tokens.quoted("referer")?);
+        entry.user_agent = optional(tokens.quoted("user agent")?);
+        tokens.end()?;
+        Ok(entry)
+    }
+}
+
+fn parse_common(tokens: &mut Tokens) -> Result<AccessLogEntry, ParseFailure> {
+    let host = tokens.word("host")?.to_owned();
+    let ident = optional(tokens.word("ident")?.to_owned());
+    let user = optional(tokens.word("user")?.to_owned());
+    let timestamp =
+        DateTime::parse_from_str(tokens.bracketed("timestamp")?, "%d/%b/%Y:%H:%M:%S %z")?;
+    let request = tokens.quoted("request")?;
+    let status = tokens.word("status")?.parse()?;
+    let bytes = match tokens.word("bytes")? {
+        "-" => None,
+        bytes => Some(bytes.parse()?),
+    };
+
+    let mut parts = request.split(' ');
+    let (method, path, protocol) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), Some(protocol), None) => (
+            Some(method.to_owned()),
+            Some(path.to_owned()),
+            Some(protocol.to_owned()),
+        ),
+        _ => (None, None, None),
+    };
+
+    Ok(AccessLogEntry {
+        host,
+        ident,
+        user,
+        timestamp,
+        request,
+        method,
+        path,
+        protocol,
+        status,
+        bytes,
+        referer: None,
+        user_agent: None,
+    })
+}
+
+fn optional(value: String) -> Option<String> {
+    (value != "-").then_some(value)
+}
+
+/// Splits an access log line into its space separated tokens.
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { rest: line }
+    }
+
+    fn word(&mut self, name: &str) -> Result<&'a str, ParseFailure> {
+        self.rest = self.rest.trim_start_matches(' ');
+        let end = self.rest.find(' ').unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(end);
+        if word.is_empty() {
+            return Err(format!("missing {}", name).into());
+        }
+        self.rest = rest;
+        Ok(word)
+    }
+
+    fn bracketed(&mut self, name: &str) -> Result<&'a str, ParseFailure> {
+        self.rest = self.rest.trim_start_matches(' ');
+        let inner = self
+            .rest
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'));
+        match inner {
+            Some((inner, rest)) => {
+                self.rest = rest;
+                Ok(inner)
+            }
+            None => Err(format!("missing {}", name).into()),
+        }
+    }
+
+    /// A double quoted field, in which `\"` and `\\` are escapes.
+    fn quoted(&mut self, name: &str) -> Result<String, ParseFailure> {
+        self.rest = self.rest.trim_start_matches(' ');
+        let mut chars = match self.rest.strip_prefix('"') {
+            Some(rest) => rest.char_indices(),
+            None => return Err(format!("missing {}", name).into()),
+        };
+        let mut value = String::new();
+
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[index + 2..];
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => value.push(c),
+                    Some((_, c)) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+
+        Err(format!("unterminated {}", name).into())
+    }
+
+    fn end(&self) -> Result<(), ParseFailure> {
+        match self.rest.trim() {
+            "" => Ok(()),
+            rest => Err(format!("unexpected trailing data {:?}", rest).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMON: &str =
+        r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /a.gif HTTP/1.0" 200 2326"#;
+
+    #[test]
+    fn common_log() {
+        let entry = CommonLog.parse(COMMON.as_bytes()).unwrap();
+        assert_eq!(entry.host, "127.0.0.1");
+        assert_eq!(entry.ident, None);
+        assert_eq!(entry.user.as_deref(), Some("frank"));
+        assert_eq!(entry.timestamp.to_rfc3339(), "2000-10-10T13:55:36-07:00");
+        assert_eq!(entry.method.as_deref(), Some("GET"));
+        assert_eq!(entry.path.as_deref(), Some("/a.gif"));
+        assert_eq!(entry.status, 200);
+        assert_eq!(entry.bytes, Some(2326));
+
+        // A request line that is not `method path protocol` is only kept whole.
+        let line = r#"h - - [10/Oct/2000:13:55:36 -0700] "\x16\x03" 400 -"#;
+        let entry = CommonLog.parse(line.as_bytes()).unwrap();
+        assert_eq!(entry.request, r"\x16\x03");
+        assert_eq!(entry.method, None);
+        assert_eq!(entry.bytes, None);
+    }
+
+    #[test]
+    fn combined_log() {
+        let line = format!(r#"{COMMON} "http://example.com/" "curl \"8.0\"""#);
+        let entry = CombinedLog.parse(line.as_bytes()).unwrap();
+        assert_eq!(entry.referer.as_deref(), Some("http://example.com/"));
+        assert_eq!(entry.user_agent.as_deref(), Some(r#"curl "8.0""#));
+
+        let line = format!(r#"{COMMON} "-" "-""#);
+        let entry = CombinedLog.parse(line.as_bytes()).unwrap();
+        assert_eq!(entry.referer, None);
+        assert_eq!(entry.user_agent, None);
+    }
+
+    #[test]
+    fn wrong_field_counts_are_rejected() {
+        // Without the bytes, with one field too many, and a combined line as common.
+        let missing = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200"#;
+        assert!(CommonLog.parse(missing.as_bytes()).is_err());
+        assert!(CommonLog.parse(format!("{COMMON} 7").as_bytes()).is_err());
+        let combined = format!(r#"{COMMON} "-" "curl""#);
+        assert!(CommonLog.parse(combined.as_bytes()).is_err());
+        assert!(CombinedLog.parse(COMMON.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        for line in [
+            // Truncated in the request, and right after an escape.
+            r#"h - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0"#,
+            r#"h - - [10/Oct/2000:13:55:36 -0700] "GET \"#,
+            r#"h - - [10/Oct/2000:13:55:36 -0700 "GET / HTTP/1.0" 200 1"#,
+            r#"h - - [10/Oct/2000:13:55:36] "GET / HTTP/1.0" 200 1"#,
+            r#"h - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" OK 1"#,
+            r#"h - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -1"#,
+        ] {
+            assert!(CommonLog.parse(line.as_bytes()).is_err(), "{line}");
+        }
+    }
+}
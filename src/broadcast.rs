@@ -0,0 +1,118 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use tokio::sync::{broadcast, mpsc::error::TryRecvError};
+
+#[derive(Debug)]
+pub(crate) struct Broadcast {
+    sender: broadcast::Sender<Vec<u8>>,
+    replay: Mutex<VecDeque<Vec<u8>>>,
+    replay_len: usize,
+}
+
+impl Broadcast {
+    pub(crate) fn new(capacity: usize, replay_len: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self {
+            sender,
+            replay: Mutex::new(VecDeque::with_capacity(replay_len)),
+            replay_len,
+        }
+    }
+
+    pub(crate) fn send(&self, data: Vec<u8>) {
+        // Holding the replay lock while sending keeps `subscribe` from seeing a message
+        // both in its replay and in its channel.
+        let mut replay = self.replay.lock().unwrap();
+
+        if self.replay_len > 0 {
+            if replay.len() == self.replay_len {
+                replay.pop_front();
+            }
+            replay.push_back(data.clone());
+        }
+
+        // No subscribers is not an error, the data is simply dropped.
+        self.sender.send(data).ok();
+    }
+
+    pub(crate) fn subscribe(&self, with_replay: bool) -> LogSubscriber {
+        let replay = self.replay.lock().unwrap();
+
+        LogSubscriber {
+            backlog: if with_replay {
+                replay.clone()
+            } else {
+                VecDeque::new()
+            },
+            receiver: self.sender.subscribe(),
+            lagged: 0,
+        }
+    }
+}
+
+/// One consumer of a broadcasting `LogWatcher`.
+///
+/// A subscriber that falls more than the broadcast capacity behind skips the oldest
+/// messages instead of slowing down the others. Skipped messages are counted in
+/// [`LogSubscriber::lagged`].
+#[derive(Debug)]
+pub struct LogSubscriber {
+    backlog: VecDeque<Vec<u8>>,
+    receiver: broadcast::Receiver<Vec<u8>>,
+    lagged: u64,
+}
+
+impl LogSubscriber {
+    /// A subscriber whose stream has already ended.
+    pub(crate) fn ended() -> Self {
+        let (_, receiver) = broadcast::channel(1);
+
+        Self {
+            backlog: VecDeque::new(),
+            receiver,
+            lagged: 0,
+        }
+    }
+
+    pub async fn recv(&mut self) -> Option<Vec<u8>> {
+        if let Some(data) = self.backlog.pop_front() {
+            return Some(data);
+        }
+
+        loop {
+            match self.receiver.recv().await {
+                Ok(data) => return Some(data),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => self.lagged += skipped,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<Vec<u8>, TryRecvError> {
+        if let Some(data) = self.backlog.pop_front() {
+            return Ok(data);
+        }
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(data) => return Ok(data),
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => self.lagged += skipped,
+                Err(broadcast::error::TryRecvError::Empty) => return Err(TryRecvError::Empty),
+                Err(broadcast::error::TryRecvError::Closed) => {
+                    return Err(TryRecvError::Disconnected)
+                }
+            }
+        }
+    }
+
+    /// Total number of messages this subscriber missed by falling behind.
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    /// Number of messages waiting to be received by this subscriber.
+    pub fn pending(&self) -> usize {
+        self.backlog.len() + self.receiver.len()
+    }
+}
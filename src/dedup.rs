@@ -0,0 +1,204 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+use tokio::time::{sleep_until, Instant};
+
+use crate::records::{ParseError, RecordParser, RecordTime, Records};
+
+/// How [`Records::dedup`] collapses repeated lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dedup {
+    window: Duration,
+    mask_variables: bool,
+}
+
+impl Dedup {
+    /// Collapses repeats of a line within `window` of its first occurrence, going by the
+    /// time of the records when both have one and by when they were read otherwise.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            mask_variables: false,
+        }
+    }
+
+    /// Also takes lines differing only by their numbers and UUIDs as repeats.
+    /// Defaults to false.
+    pub fn mask_variables(self, mask_variables: bool) -> Self {
+        Self {
+            mask_variables,
+            ..self
+        }
+    }
+}
+
+/// A record standing for `repeats` consecutive repeats of its line, 1 unless collapsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repeated<R> {
+    pub record: R,
+    pub repeats: u64,
+}
+
+/// Records of a [`Records`] with consecutive repeats of a line collapsed into its first
+/// occurrence, like syslog's "last message repeated N times".
+///
+/// The first occurrence is held back and its repeats counted until a different line is
+/// read, the window since it was read runs out, or the receiver is closed. Lines are
+/// compared before parsing, so records of any type can be collapsed, and lines that
+/// fail to parse never are.
+#[derive(Debug)]
+pub struct Deduplicated<P: RecordParser> {
+    records: Records<P>,
+    dedup: Dedup,
+    held: Option<Held<P::Record>>,
+    ready: VecDeque<Result<Repeated<P::Record>, ParseError>>,
+}
+
+#[derive(Debug)]
+struct Held<T> {
+    key: Vec<u8>,
+    read_at: Instant,
+    time: Option<SystemTime>,
+    repeated: Repeated<T>,
+}
+
+impl<T> Held<T> {
+    /// Whether a repeat read at `read_at` with `time` is still within `window`.
+    fn within(&self, window: Duration, read_at: Instant, time: Option<SystemTime>) -> bool {
+        match (self.time, time) {
+            (Some(first), Some(time)) => {
+                let elapsed = time
+                    .duration_since(first)
+                    .unwrap_or_else(|earlier| earlier.duration());
+                elapsed <= window
+            }
+            _ => read_at.duration_since(self.read_at) <= window,
+        }
+    }
+}
+
+impl<P: RecordParser> Records<P> {
+    pub fn dedup(self, dedup: Dedup) -> Deduplicated<P> {
+        Deduplicated {
+            records: self,
+            dedup,
+            held: None,
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+impl<P> Deduplicated<P>
+where
+    P: RecordParser,
+    P::Record: RecordTime,
+{
+    /// Returns `None` once the receiver is closed and every line was parsed.
+    pub async fn next(&mut self) -> Option<Result<Repeated<P::Record>, ParseError>> {
+        loop {
+            if let Some(result) = self.ready.pop_front() {
+                return Some(result);
+            }
+
+            let deadline = self
+                .held
+                .as_ref()
+                .map(|held| held.read_at + self.dedup.window);
+            let line = match deadline {
+                Some(deadline) => tokio::select! {
+                    biased;
+                    _ = sleep_until(deadline) => {
+                        return self.held.take().map(|held| Ok(held.repeated));
+                    }
+                    line = self.records.next_line() => line,
+                },
+                None => self.records.next_line().await,
+            };
+
+            let Some(line) = line else {
+                return self.held.take().map(|held| Ok(held.repeated));
+            };
+
+            let read_at = Instant::now();
+            let key = match self.dedup.mask_variables {
+                true => mask_variables(&line),
+                false => line.clone(),
+            };
+
+            let previous = match self.records.parse(line) {
+                Some(Ok(record)) => {
+                    let time = record.record_time();
+                    if let Some(held) = &mut self.held {
+                        if held.key == key && held.within(self.dedup.window, read_at, time) {
+                            held.repeated.repeats += 1;
+                            continue;
+                        }
+                    }
+
+                    self.held.replace(Held {
+                        key,
+                        read_at,
+                        time,
+                        repeated: Repeated { record, repeats: 1 },
+                    })
+                }
+                Some(Err(error)) => {
+                    self.ready.push_back(Err(error));
+                    self.held.take()
+                }
+                None => self.held.take(),
+            };
+
+            if let Some(previous) = previous {
+                return Some(Ok(previous.repeated));
+            }
+        }
+    }
+
+    /// Gives the records back. A held record is lost.
+    pub fn into_inner(self) -> Records<P> {
+        self.records
+    }
+}
+
+/// `line` with UUIDs replaced by `<uuid>` and runs of digits by `#`.
+fn mask_variables(line: &[u8]) -> Vec<u8> {
+    let mut masked = Vec::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(&byte) = rest.first() {
+        let starts_word = masked
+            .last()
+            .is_none_or(|previous: &u8| !previous.is_ascii_alphanumeric());
+
+        if starts_word && is_uuid(rest) {
+            masked.extend_from_slice(b"<uuid>");
+            rest = &rest[36..];
+        } else if byte.is_ascii_digit() {
+            let digits = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+            masked.push(b'#');
+            rest = &rest[digits..];
+        } else {
+            masked.push(byte);
+            rest = &rest[1..];
+        }
+    }
+
+    masked
+}
+
+fn is_uuid(data: &[u8]) -> bool {
+    data.len() >= 36
+        && data[..36]
+            .iter()
+            .enumerate()
+            .all(|(index, byte)| match index {
+                8 | 13 | 18 | 23 => *byte == b'-',
+                _ => byte.is_ascii_hexdigit(),
+            })
+        && data
+            .get(36)
+            .is_none_or(|byte| !byte.is_ascii_alphanumeric())
+}
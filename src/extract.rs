@@ -0,0 +1,232 @@
+use std::{borrow::Cow, collections::HashMap};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use regex::Regex;
+
+use crate::records::{ParseFailure, RecordFields, RecordParser, RecordTime};
+
+/// Type a captured field is converted to by [`RegexParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FieldType {
+    String,
+    Int,
+    Float,
+    /// `true`, `yes`, `on` and `1`, or `false`, `no`, `off` and `0`, in any case.
+    Bool,
+    /// A strftime format. Timestamps without an offset are taken as UTC.
+    #[cfg(feature = "chrono")]
+    Timestamp(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum FieldValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    #[cfg(feature = "chrono")]
+    Timestamp(DateTime<FixedOffset>),
+}
+
+/// Named fields extracted by [`RegexParser`], in the order of their groups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypedFields(Vec<(String, FieldValue)>);
+
+impl TypedFields {
+    /// Groups that did not take part in the match are missing.
+    pub fn get(&self, key: &str) -> Option<&FieldValue> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FieldValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl RecordTime for TypedFields {}
+
+/// Typed values are formatted back to text, timestamps as RFC 3339.
+impl RecordFields for TypedFields {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        Some(match self.get(name)? {
+            FieldValue::String(value) => Cow::Borrowed(value),
+            FieldValue::Int(value) => Cow::Owned(value.to_string()),
+            FieldValue::Float(value) => Cow::Owned(value.to_string()),
+            FieldValue::Bool(value) => Cow::Owned(value.to_string()),
+            #[cfg(feature = "chrono")]
+            FieldValue::Timestamp(value) => Cow::Owned(value.to_rfc3339()),
+        })
+    }
+}
+
+impl IntoIterator for TypedFields {
+    type Item = (String, FieldValue);
+    type IntoIter = std::vec::IntoIter<(String, FieldValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Extracts the named capture groups of a regex from each line, for formats without a
+/// dedicated parser.
+///
+/// Groups are strings unless given a [`FieldType`]. Lines the regex does not match, or
+/// whose fields fail to convert, are parse errors.
+#[derive(Debug, Clone)]
+pub struct RegexParser {
+    regex: Regex,
+    types: HashMap<String, FieldType>,
+}
+
+impl RegexParser {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: Regex::new(pattern)?,
+            types: HashMap::new(),
+        })
+    }
+
+    pub fn field(mut self, name: impl Into<String>, field_type: FieldType) -> Self {
+        self.types.insert(name.into(), field_type);
+        self
+    }
+}
+
+impl RecordParser for RegexParser {
+    type Record = TypedFields;
+
+    fn parse(&self, line: &[u8]) -> Result<TypedFields, ParseFailure> {
+        let line = std::str::from_utf8(line)?;
+        let captures = self.regex.captures(line).ok_or("line does not match")?;
+        let mut fields = Vec::new();
+
+        for name in self.regex.capture_names().flatten() {
+            let text = match captures.name(name) {
+                Some(text) => text.as_str(),
+                None => continue,
+            };
+            let value = match self.types.get(name) {
+                None | Some(FieldType::String) => FieldValue::String(text.to_owned()),
+                Some(field_type) => convert(text, field_type)
+                    .map_err(|err| format!("invalid field {}: {}", name, err))?,
+            };
+            fields.push((name.to_owned(), value));
+        }
+
+        Ok(TypedFields(fields))
+    }
+}
+
+fn convert(text: &str, field_type: &FieldType) -> Result<FieldValue, ParseFailure> {
+    Ok(match field_type {
+        FieldType::String => FieldValue::String(text.to_owned()),
+        FieldType::Int => FieldValue::Int(text.parse()?),
+        FieldType::Float => FieldValue::Float(text.parse()?),
+        FieldType::Bool => match text.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => FieldValue::Bool(true),
+            "false" | "no" | "off" | "0" => FieldValue::Bool(false),
+            _ => return Err(format!("{:?} is not a boolean", text).into()),
+        },
+        #[cfg(feature = "chrono")]
+        FieldType::Timestamp(format) => {
+            FieldValue::Timestamp(match DateTime::parse_from_str(text, format) {
+                Ok(timestamp) => timestamp,
+                Err(_) => NaiveDateTime::parse_from_str(text, format)?
+                    .and_utc()
+                    .fixed_offset(),
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser() -> RegexParser {
+        RegexParser::new(
+            r"^(?P<level>\w+) (?P<code>\S+) (?P<ratio>\S+) (?P<ok>\w+)(?: (?P<extra>.+))?$",
+        )
+        .unwrap()
+        .field("code", FieldType::Int)
+        .field("ratio", FieldType::Float)
+        .field("ok", FieldType::Bool)
+    }
+
+    #[test]
+    fn typed_fields() {
+        let fields = parser().parse(b"warn -3 0.5 YES").unwrap();
+        assert_eq!(
+            fields.iter().collect::<Vec<_>>(),
+            [
+                ("level", &FieldValue::String("warn".to_owned())),
+                ("code", &FieldValue::Int(-3)),
+                ("ratio", &FieldValue::Float(0.5)),
+                ("ok", &FieldValue::Bool(true)),
+            ]
+        );
+        // An optional group that did not match is missing, not empty.
+        assert_eq!(fields.get("extra"), None);
+        assert_eq!(fields.field("code").as_deref(), Some("-3"));
+    }
+
+    #[test]
+    fn invalid_coercions_are_rejected() {
+        for line in [
+            &b"warn 3.5 0.5 yes"[..],
+            b"warn 99999999999999999999 0.5 yes",
+            b"warn 3 half yes",
+            b"warn 3 0.5 maybe",
+        ] {
+            let error = parser().parse(line).unwrap_err().to_string();
+            assert!(error.starts_with("invalid field"), "{:?}: {}", line, error);
+        }
+    }
+
+    #[test]
+    fn unmatched_lines_are_rejected() {
+        assert!(parser().parse(b"warn").is_err());
+        assert!(parser().parse(b"warn 3 0.5 yes \xff").is_err());
+        assert!(RegexParser::new("(unclosed").is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn timestamps() {
+        let parser = RegexParser::new(r"^(?P<time>[^|]+)\|").unwrap().field(
+            "time",
+            FieldType::Timestamp("%Y-%m-%d %H:%M:%S%.f %z".to_owned()),
+        );
+        let fields = parser.parse(b"2024-02-29 12:00:00.5 +0200|").unwrap();
+        assert_eq!(
+            fields.field("time").as_deref(),
+            Some("2024-02-29T12:00:00.500+02:00")
+        );
+
+        // Without an offset in the format the timestamp is UTC.
+        let parser = RegexParser::new(r"^(?P<time>[^|]+)\|")
+            .unwrap()
+            .field("time", FieldType::Timestamp("%Y-%m-%d %H:%M:%S".to_owned()));
+        let fields = parser.parse(b"2024-02-29 12:00:00|").unwrap();
+        assert_eq!(
+            fields.field("time").as_deref(),
+            Some("2024-02-29T12:00:00+00:00")
+        );
+        assert!(parser.parse(b"2023-02-29 12:00:00|").is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    io::SeekFrom,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "gzip")]
+use async_compression::tokio::bufread::GzipDecoder;
+#[cfg(feature = "zstd")]
+use async_compression::tokio::bufread::ZstdDecoder;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use tokio::io::BufReader;
+#[cfg(unix)]
+use tokio::net::unix::pipe;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, ReadBuf, Stdin},
+};
+
+use crate::source::BoxedSource;
+
+/// Path that reads the standard input of the process instead of a file.
+pub(crate) const STDIN_PATH: &str = "-";
+
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How the content of a file is encoded.
+///
+/// Compressed files are read to their end once and never polled for growth.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Detects compressed files by their magic bytes, for the enabled formats.
+    #[default]
+    Auto,
+    Plain,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// File read by a `LogBufReader`, either as is or through a decompressor.
+///
+/// Only plain files seek and rotate. Pipes and devices are read as they come.
+#[derive(Debug)]
+pub(crate) enum LogFile {
+    Plain(File),
+    /// A named pipe. It stays open between writers, so a new writer simply resumes it.
+    #[cfg(unix)]
+    Fifo(pipe::Receiver),
+    /// A character device such as `/dev/kmsg`.
+    Device(File),
+    Stdin(Stdin),
+    /// A reader plugged in through `LogWatcher::from_source`.
+    Source(BoxedSource),
+    #[cfg(feature = "gzip")]
+    Gzip(GzipDecoder<BufReader<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdDecoder<BufReader<File>>),
+}
+
+impl LogFile {
+    pub(crate) async fn open(
+        path: &Path,
+        compression: Compression,
+    ) -> Result<Self, std::io::Error> {
+        if path == Path::new(STDIN_PATH) {
+            return Ok(LogFile::Stdin(tokio::io::stdin()));
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            let file_type = tokio::fs::metadata(path).await?.file_type();
+
+            if file_type.is_fifo() {
+                let mut options = pipe::OpenOptions::new();
+                // Holding the write end too keeps the pipe from reporting EOF between
+                // writers, so the reader just waits for the next one.
+                #[cfg(target_os = "linux")]
+                options.read_write(true);
+                return Ok(LogFile::Fifo(options.open_receiver(path)?));
+            }
+            if file_type.is_char_device() {
+                return Ok(LogFile::Device(File::open(path).await?));
+            }
+        }
+
+        let mut file = File::open(path).await?;
+
+        let compression = match compression {
+            Compression::Auto => detect(&mut file).await?,
+            compression => compression,
+        };
+
+        Ok(match compression {
+            Compression::Auto | Compression::Plain => LogFile::Plain(file),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut decoder = GzipDecoder::new(BufReader::new(file));
+                decoder.multiple_members(true);
+                LogFile::Gzip(decoder)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut decoder = ZstdDecoder::new(BufReader::new(file));
+                decoder.multiple_members(true);
+                LogFile::Zstd(decoder)
+            }
+        })
+    }
+
+    /// Whether the file is read once to its end instead of followed.
+    pub(crate) fn is_finite(&self) -> bool {
+        match self {
+            LogFile::Stdin(_) => true,
+            LogFile::Source(source) => source.source.is_finite(),
+            #[cfg(feature = "gzip")]
+            LogFile::Gzip(_) => true,
+            #[cfg(feature = "zstd")]
+            LogFile::Zstd(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the file is a pipe, a device or a plugged in source, which has no end to
+    /// read up to.
+    pub(crate) fn is_stream(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            LogFile::Fifo(_) => true,
+            LogFile::Device(_) | LogFile::Stdin(_) | LogFile::Source(_) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_source(&self) -> bool {
+        matches!(self, LogFile::Source(_))
+    }
+
+    /// Whether the file is a regular file, which can seek and be rotated.
+    pub(crate) fn is_regular(&self) -> bool {
+        matches!(self, LogFile::Plain(_))
+    }
+}
+
+/// Whether `metadata` describes something `LogFile::open` can read from.
+pub(crate) fn is_readable(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        let file_type = metadata.file_type();
+        if file_type.is_fifo() || file_type.is_char_device() {
+            return true;
+        }
+    }
+
+    metadata.is_file()
+}
+
+/// Guesses the compression of `file` from its first bytes and rewinds it.
+async fn detect(file: &mut File) -> Result<Compression, std::io::Error> {
+    let mut magic = [0; 4];
+    let mut len = 0;
+
+    while len < magic.len() {
+        match file.read(&mut magic[len..]).await? {
+            0 => break,
+            read => len += read,
+        }
+    }
+    file.seek(SeekFrom::Start(0)).await?;
+
+    #[cfg(feature = "gzip")]
+    if magic[..len].starts_with(GZIP_MAGIC) {
+        return Ok(Compression::Gzip);
+    }
+    #[cfg(feature = "zstd")]
+    if magic[..len].starts_with(ZSTD_MAGIC) {
+        return Ok(Compression::Zstd);
+    }
+
+    Ok(Compression::Plain)
+}
+
+impl AsyncRead for LogFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            LogFile::Plain(file) => Pin::new(file).poll_read(cx, buf),
+            #[cfg(unix)]
+            LogFile::Fifo(pipe) => Pin::new(pipe).poll_read(cx, buf),
+            LogFile::Device(file) => Pin::new(file).poll_read(cx, buf),
+            LogFile::Stdin(stdin) => Pin::new(stdin).poll_read(cx, buf),
+            LogFile::Source(source) => Pin::new(&mut *source.source).poll_read(cx, buf),
+            #[cfg(feature = "gzip")]
+            LogFile::Gzip(decoder) => Pin::new(decoder).poll_read(cx, buf),
+            #[cfg(feature = "zstd")]
+            LogFile::Zstd(decoder) => Pin::new(decoder).poll_read(cx, buf),
+        }
+    }
+}
+
+/// Only plain files can seek, the others fail with `Unsupported`.
+impl AsyncSeek for LogFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        match self.get_mut() {
+            LogFile::Plain(file) => Pin::new(file).start_seek(position),
+            _ => Err(std::io::ErrorKind::Unsupported.into()),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        match self.get_mut() {
+            LogFile::Plain(file) => Pin::new(file).poll_complete(cx),
+            _ => Poll::Ready(Err(std::io::ErrorKind::Unsupported.into())),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt, sync::Arc, sync::Mutex};
+
+#[cfg(feature = "aho-corasick")]
+use aho_corasick::AhoCorasick;
+
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::{
+    records::{RecordFields, RecordParser},
+    sender::{rejected, LogSender},
+};
+
+/// Matches a line, without its line ending, for the include and exclude rules of a
+/// [`LineFilter`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Matcher {
+    /// Matches lines containing the text.
+    Substring(Vec<u8>),
+    #[cfg(feature = "regex")]
+    Regex(regex::bytes::Regex),
+    /// Matches lines containing any of its patterns.
+    #[cfg(feature = "aho-corasick")]
+    Literals(AhoCorasick),
+}
+
+impl From<&str> for Matcher {
+    fn from(text: &str) -> Self {
+        Matcher::Substring(text.as_bytes().to_vec())
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::bytes::Regex> for Matcher {
+    fn from(regex: regex::bytes::Regex) -> Self {
+        Matcher::Regex(regex)
+    }
+}
+
+#[cfg(feature = "aho-corasick")]
+impl From<AhoCorasick> for Matcher {
+    fn from(literals: AhoCorasick) -> Self {
+        Matcher::Literals(literals)
+    }
+}
+
+impl Matcher {
+    fn is_match(&self, line: &[u8]) -> bool {
+        match *self {
+            Matcher::Substring(ref text) => {
+                text.is_empty() || line.windows(text.len()).any(|window| window == text)
+            }
+            #[cfg(feature = "regex")]
+            Matcher::Regex(ref regex) => regex.is_match(line),
+            #[cfg(feature = "aho-corasick")]
+            Matcher::Literals(ref literals) => literals.is_match(line),
+        }
+    }
+}
+
+type Predicate = Arc<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+/// Decides which lines a watcher delivers, inside its loop so that dropped lines never
+/// reach the channel.
+///
+/// A line is kept when it matches one of the include rules, if there are any, none of
+/// the exclude rules, and every predicate. A line split across reads, as happens at the
+/// end of a file still being written, is decided on its first part.
+#[derive(Clone, Default)]
+pub struct LineFilter {
+    includes: Vec<Matcher>,
+    excludes: Vec<Matcher>,
+    predicates: Vec<Predicate>,
+}
+
+impl LineFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include(mut self, matcher: impl Into<Matcher>) -> Self {
+        self.includes.push(matcher.into());
+        self
+    }
+
+    pub fn exclude(mut self, matcher: impl Into<Matcher>) -> Self {
+        self.excludes.push(matcher.into());
+        self
+    }
+
+    pub fn predicate(mut self, predicate: impl Fn(&[u8]) -> bool + Send + Sync + 'static) -> Self {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    /// Keeps the lines for which `predicate` holds on the field `name` of the record
+    /// parsed by `parser`, or on `None` when the line does not parse or lacks the field.
+    pub fn field<P>(
+        self,
+        parser: P,
+        name: impl Into<String>,
+        predicate: impl Fn(Option<&str>) -> bool + Send + Sync + 'static,
+    ) -> Self
+    where
+        P: RecordParser + Send + Sync + 'static,
+        P::Record: RecordFields,
+    {
+        let name = name.into();
+        self.predicate(move |line| match parser.parse(line) {
+            Ok(record) => predicate(record.field(&name).as_deref()),
+            Err(_) => predicate(None),
+        })
+    }
+
+    fn keeps(&self, line: &[u8]) -> bool {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        (self.includes.is_empty() || self.includes.iter().any(|rule| rule.is_match(line)))
+            && !self.excludes.iter().any(|rule| rule.is_match(line))
+            && self.predicates.iter().all(|predicate| predicate(line))
+    }
+}
+
+impl fmt::Debug for LineFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineFilter")
+            .field("includes", &self.includes)
+            .field("excludes", &self.excludes)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+/// A sender delivering only the lines its filter keeps.
+#[derive(Debug)]
+pub(crate) struct Filtered {
+    pub(crate) filter: LineFilter,
+    pub(crate) sender: LogSender,
+    /// Whether the unterminated line ending the last message was kept.
+    continued: Mutex<Option<bool>>,
+}
+
+impl Filtered {
+    pub(crate) fn new(filter: LineFilter, sender: LogSender) -> Self {
+        Self {
+            filter,
+            sender,
+            continued: Mutex::new(None),
+        }
+    }
+
+    /// Sends the lines of `data` that are kept. Data the channel turns away is given
+    /// back, and the line it ends with is decided again when it is sent again.
+    pub(crate) fn send(&self, data: Vec<u8>) -> Result<(), TrySendError<Vec<u8>>> {
+        let mut continued = self.continued.lock().unwrap();
+        let mut next = *continued;
+        let mut kept = Vec::new();
+
+        for line in data.split_inclusive(|&byte| byte == b'\n') {
+            let keep = match next.take() {
+                Some(keep) => keep,
+                None => self.filter.keeps(line),
+            };
+            if keep {
+                kept.extend_from_slice(line);
+            }
+            if !line.ends_with(b"\n") {
+                next = Some(keep);
+            }
+        }
+
+        if !kept.is_empty() {
+            self.sender
+                .try_send(kept)
+                .map_err(|err| rejected(err, data))?;
+        }
+        *continued = next;
+        Ok(())
+    }
+}
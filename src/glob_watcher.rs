@@ -0,0 +1,318 @@
+use std::{
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use glob::{MatchOptions, Pattern, PatternError};
+use tokio::{
+    sync::{
+        mpsc::{error::TryRecvError, Receiver, Sender, UnboundedSender},
+        watch,
+    },
+    time::{interval, MissedTickBehavior},
+};
+
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    multiplex::{FileError, Multiplexer},
+    Compression, FollowMode, LogReaderMode, ReadOptions, RestartPolicy, Shutdown, SpawnFnResult,
+    TaggedMessage,
+};
+
+pub struct GlobWatcherBuilder {
+    pattern: String,
+    mode: LogReaderMode,
+    skip_to_end: bool,
+    rescan_interval: Duration,
+    restart_policy: RestartPolicy,
+    compression: Compression,
+    errors: Option<UnboundedSender<FileError>>,
+    #[cfg(feature = "tokio-util")]
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl GlobWatcherBuilder {
+    pub fn mode(self, mode: LogReaderMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Applies to the files matched by the first scan. Files showing up later are
+    /// always read from the start.
+    pub fn skip_to_end(self, skip_to_end: bool) -> Self {
+        Self {
+            skip_to_end,
+            ..self
+        }
+    }
+
+    /// How often the pattern is expanded again to pick up new files and retire vanished
+    /// ones. Defaults to 5 seconds.
+    pub fn rescan_interval(self, rescan_interval: Duration) -> Self {
+        Self {
+            rescan_interval,
+            ..self
+        }
+    }
+
+    /// Applied to each file on its own. See [`crate::LogWatcherBuilder::restart_policy`].
+    pub fn restart_policy(self, restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            ..self
+        }
+    }
+
+    /// See [`crate::LogWatcherBuilder::compression`]. Matched files that were read to
+    /// their end stay listed until they vanish.
+    pub fn compression(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    /// Sends the error of each file that stops, once its restart policy gave up, to
+    /// `errors`. A stopped file stays listed, and is not read again, until it vanishes.
+    pub fn route_errors(self, errors: UnboundedSender<FileError>) -> Self {
+        Self {
+            errors: Some(errors),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "tokio-util")]
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation_token: Some(token),
+            ..self
+        }
+    }
+
+    pub fn build(self) -> Result<GlobWatcher, PatternError> {
+        let segments = Path::new(&self.pattern)
+            .components()
+            .map(Segment::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(4096);
+        let (shutdown_tx, _) = watch::channel(false);
+        let (paths_tx, paths_rx) = watch::channel(Vec::new());
+
+        Ok(GlobWatcher {
+            receiver,
+            loop_parts: Mutex::new(Some((sender, paths_tx))),
+            segments,
+            shutdown_tx,
+            paths_rx,
+            mode: self.mode,
+            skip_to_end: self.skip_to_end,
+            rescan_interval: self.rescan_interval,
+            restart_policy: self.restart_policy,
+            compression: self.compression,
+            errors: self.errors,
+            #[cfg(feature = "tokio-util")]
+            cancellation_token: self.cancellation_token,
+        })
+    }
+}
+
+/// Watches every file matching a glob pattern such as `/var/log/app/*.log`, and
+/// multiplexes their data into one receiver tagged with the source path.
+///
+/// All files are driven by the single future returned by [`GlobWatcher::spawn`], and a
+/// file failing does not stop the others. Wildcards may appear in any path component,
+/// `**` is not supported.
+#[derive(Debug)]
+pub struct GlobWatcher {
+    receiver: Receiver<TaggedMessage>,
+    loop_parts: Mutex<Option<LoopParts>>,
+    segments: Vec<Segment>,
+    shutdown_tx: watch::Sender<bool>,
+    paths_rx: watch::Receiver<Vec<PathBuf>>,
+    mode: LogReaderMode,
+    skip_to_end: bool,
+    rescan_interval: Duration,
+    restart_policy: RestartPolicy,
+    compression: Compression,
+    errors: Option<UnboundedSender<FileError>>,
+    #[cfg(feature = "tokio-util")]
+    cancellation_token: Option<CancellationToken>,
+}
+
+/// Channel ends owned by the spawned loop, taken on `spawn`.
+type LoopParts = (Sender<TaggedMessage>, watch::Sender<Vec<PathBuf>>);
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(OsString),
+    Pattern(Pattern),
+}
+
+impl Segment {
+    fn parse(component: Component) -> Result<Self, PatternError> {
+        let text = component.as_os_str().to_string_lossy();
+
+        if text.contains(['*', '?', '[']) {
+            Ok(Segment::Pattern(Pattern::new(&text)?))
+        } else {
+            Ok(Segment::Literal(component.as_os_str().to_owned()))
+        }
+    }
+}
+
+impl GlobWatcher {
+    pub fn builder(pattern: impl Into<String>) -> GlobWatcherBuilder {
+        GlobWatcherBuilder {
+            pattern: pattern.into(),
+            mode: LogReaderMode::ReadToEnd,
+            skip_to_end: true,
+            rescan_interval: Duration::from_secs(5),
+            restart_policy: RestartPolicy::Never,
+            compression: Compression::Auto,
+            errors: None,
+            #[cfg(feature = "tokio-util")]
+            cancellation_token: None,
+        }
+    }
+
+    /// Returns `None` once the spawned loop has exited and everything was read.
+    pub async fn read_message(&mut self) -> Option<TaggedMessage> {
+        self.receiver.recv().await
+    }
+
+    pub fn try_read_message(&mut self) -> Result<TaggedMessage, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Files matched by the last scan.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.paths_rx.borrow().clone()
+    }
+
+    /// Stops the spawned loop and waits for it to exit.
+    pub async fn shutdown(self) {
+        self.shutdown_tx.send_replace(true);
+        self.shutdown_tx.closed().await;
+    }
+
+    pub fn spawn(&self) -> SpawnFnResult {
+        let (sender, paths_tx) = match self.loop_parts.lock().unwrap().take() {
+            Some(loop_parts) => loop_parts,
+            None => panic!("Glob watcher spawned twice {:?}", self),
+        };
+
+        let segments = self.segments.clone();
+        let options = ReadOptions {
+            mode: self.mode,
+            compression: self.compression,
+            follow: FollowMode::Name,
+        };
+        let mut skip_to_end = self.skip_to_end;
+        let rescan_interval = self.rescan_interval;
+        let restart_policy = self.restart_policy;
+        let errors = self.errors.clone();
+        let mut shutdown = Shutdown {
+            rx: self.shutdown_tx.subscribe(),
+            #[cfg(feature = "tokio-util")]
+            token: self.cancellation_token.clone(),
+        };
+
+        Box::pin(async move {
+            let mut files = Multiplexer::new(sender.clone());
+            let mut rescan = interval(rescan_interval);
+            rescan.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = shutdown.requested() => break,
+                    _ = sender.closed() => break,
+                    _ = rescan.tick() => {
+                        let found = expand(&segments).await;
+
+                        for path in files.paths() {
+                            if !found.contains(&path) {
+                                files.remove(&path);
+                            }
+                        }
+                        for path in found {
+                            if !files.contains(&path) {
+                                files.add(path, options, skip_to_end, restart_policy);
+                            }
+                        }
+                        skip_to_end = false;
+
+                        paths_tx.send_replace(files.paths());
+                    }
+                    failed = files.step() => if let Some(failed) = failed {
+                        if let Some(errors) = &errors {
+                            errors.send(failed).ok();
+                        }
+                    },
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Regular files matching `segments`, sorted. Directories that cannot be listed are
+/// skipped.
+async fn expand(segments: &[Segment]) -> Vec<PathBuf> {
+    let options = MatchOptions {
+        require_literal_leading_dot: true,
+        ..MatchOptions::new()
+    };
+    let mut candidates = vec![PathBuf::new()];
+
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => {
+                for candidate in &mut candidates {
+                    candidate.push(literal);
+                }
+            }
+            Segment::Pattern(pattern) => {
+                let mut matched = Vec::new();
+
+                for candidate in &candidates {
+                    let dir = if candidate.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        candidate.as_path()
+                    };
+
+                    let mut entries = match tokio::fs::read_dir(dir).await {
+                        Ok(entries) => entries,
+                        Err(_) => continue,
+                    };
+
+                    while let Ok(Some(entry)) = entries.next_entry().await {
+                        let name = entry.file_name();
+                        if pattern.matches_with(&name.to_string_lossy(), options) {
+                            matched.push(candidate.join(name));
+                        }
+                    }
+                }
+
+                candidates = matched;
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for candidate in candidates {
+        if let Ok(meta) = tokio::fs::metadata(&candidate).await {
+            if meta.is_file() {
+                files.push(candidate);
+            }
+        }
+    }
+    files.sort();
+    files
+}
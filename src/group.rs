@@ -0,0 +1,239 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{
+    mpsc::{error::TryRecvError, Receiver, Sender, UnboundedReceiver, UnboundedSender},
+    watch,
+};
+
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    multiplex::{FileError, Multiplexer},
+    Compression, FollowMode, LogReaderMode, ReadOptions, RestartPolicy, Shutdown, SpawnFnResult,
+    TaggedMessage,
+};
+
+/// Per-file settings of a [`LogWatcherGroup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchOptions {
+    mode: LogReaderMode,
+    skip_to_end: bool,
+    restart_policy: RestartPolicy,
+    compression: Compression,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            mode: LogReaderMode::ReadToEnd,
+            skip_to_end: true,
+            restart_policy: RestartPolicy::Never,
+            compression: Compression::Auto,
+        }
+    }
+}
+
+impl WatchOptions {
+    pub fn mode(self, mode: LogReaderMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub fn skip_to_end(self, skip_to_end: bool) -> Self {
+        Self {
+            skip_to_end,
+            ..self
+        }
+    }
+
+    pub fn restart_policy(self, restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            ..self
+        }
+    }
+
+    pub fn compression(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+enum GroupCommand {
+    Add(PathBuf, WatchOptions),
+    Remove(PathBuf),
+}
+
+#[derive(Default)]
+pub struct LogWatcherGroupBuilder {
+    errors: Option<UnboundedSender<FileError>>,
+    #[cfg(feature = "tokio-util")]
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl LogWatcherGroupBuilder {
+    /// Sends the error of each file that stops, once its restart policy gave up, to
+    /// `errors`. Without it, the file just stops.
+    pub fn route_errors(mut self, errors: UnboundedSender<FileError>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    #[cfg(feature = "tokio-util")]
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation_token: Some(token),
+            ..self
+        }
+    }
+
+    pub fn build(self) -> LogWatcherGroup {
+        let (sender, receiver) = tokio::sync::mpsc::channel(4096);
+        let (command_tx, command_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (shutdown_tx, _) = watch::channel(false);
+
+        LogWatcherGroup {
+            receiver,
+            command_tx,
+            loop_parts: Mutex::new(Some((sender, command_rx))),
+            paths: Default::default(),
+            errors: self.errors,
+            shutdown_tx,
+            #[cfg(feature = "tokio-util")]
+            cancellation_token: self.cancellation_token,
+        }
+    }
+}
+
+/// A dynamic set of watched files driven by a single future, with one output tagged
+/// with the source path.
+///
+/// Unlike one `LogWatcher` per file, there is no spawned task and signal channel per
+/// file, so a group can hold thousands of files. A file failing with an error its
+/// restart policy gives up on is removed from the group on its own, and the others go
+/// on.
+#[derive(Debug)]
+pub struct LogWatcherGroup {
+    receiver: Receiver<TaggedMessage>,
+    command_tx: UnboundedSender<GroupCommand>,
+    loop_parts: Mutex<Option<LoopParts>>,
+    paths: Arc<Mutex<BTreeMap<PathBuf, WatchOptions>>>,
+    errors: Option<UnboundedSender<FileError>>,
+    shutdown_tx: watch::Sender<bool>,
+    #[cfg(feature = "tokio-util")]
+    cancellation_token: Option<CancellationToken>,
+}
+
+/// Channel ends owned by the spawned loop, taken on `spawn`.
+type LoopParts = (Sender<TaggedMessage>, UnboundedReceiver<GroupCommand>);
+
+impl LogWatcherGroup {
+    pub fn builder() -> LogWatcherGroupBuilder {
+        LogWatcherGroupBuilder::default()
+    }
+
+    /// Starts watching `path`. Adding a path that is already watched does nothing.
+    /// Paths can be added before the group is spawned.
+    pub fn add(&self, path: impl Into<PathBuf>, options: WatchOptions) {
+        let path = path.into();
+        let mut paths = self.paths.lock().unwrap();
+
+        if !paths.contains_key(&path) {
+            paths.insert(path.clone(), options);
+            self.command_tx.send(GroupCommand::Add(path, options)).ok();
+        }
+    }
+
+    /// Stops watching `path`. Returns whether it was watched.
+    pub fn remove(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        let removed = self.paths.lock().unwrap().remove(path).is_some();
+
+        if removed {
+            self.command_tx
+                .send(GroupCommand::Remove(path.to_owned()))
+                .ok();
+        }
+        removed
+    }
+
+    /// Watched paths, sorted.
+    pub fn list(&self) -> Vec<PathBuf> {
+        self.paths.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub async fn read_message(&mut self) -> Option<TaggedMessage> {
+        self.receiver.recv().await
+    }
+
+    pub fn try_read_message(&mut self) -> Result<TaggedMessage, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Stops the spawned loop and waits for it to exit.
+    pub async fn shutdown(self) {
+        self.shutdown_tx.send_replace(true);
+        self.shutdown_tx.closed().await;
+    }
+
+    pub fn spawn(&self) -> SpawnFnResult {
+        let (sender, mut command_rx) = match self.loop_parts.lock().unwrap().take() {
+            Some(loop_parts) => loop_parts,
+            None => panic!("Log watcher group spawned twice {:?}", self),
+        };
+
+        let paths = self.paths.clone();
+        let errors = self.errors.clone();
+        let mut shutdown = Shutdown {
+            rx: self.shutdown_tx.subscribe(),
+            #[cfg(feature = "tokio-util")]
+            token: self.cancellation_token.clone(),
+        };
+
+        Box::pin(async move {
+            let mut files = Multiplexer::new(sender.clone());
+
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = shutdown.requested() => break,
+                    _ = sender.closed() => break,
+                    command = command_rx.recv() => match command {
+                        Some(GroupCommand::Add(path, options)) => files.add(
+                            path,
+                            ReadOptions {
+                                mode: options.mode,
+                                compression: options.compression,
+                                follow: FollowMode::Name,
+                            },
+                            options.skip_to_end,
+                            options.restart_policy,
+                        ),
+                        Some(GroupCommand::Remove(path)) => {
+                            files.remove(&path);
+                        }
+                        // The group itself was dropped.
+                        None => break,
+                    },
+                    failed = files.step() => if let Some(failed) = failed {
+                        // Removed, so that it can be added again.
+                        files.remove(&failed.path);
+                        paths.lock().unwrap().remove(&failed.path);
+                        if let Some(errors) = &errors {
+                            errors.send(failed).ok();
+                        }
+                    },
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, MapAccess, Visitor};
+
+use crate::records::{Fields, ParseFailure, RecordFields, RecordParser, RecordTime};
+
+/// Parses each line as one JSON document, into a [`serde_json::Value`] by default or
+/// into any `T: DeserializeOwned`.
+///
+/// With `JsonLines<Fields>`, the top level keys of an object become fields in document
+/// order, non-string values kept as JSON text, so it can stand in for [`crate::Logfmt`].
+pub struct JsonLines<T = serde_json::Value> {
+    record: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonLines<T> {
+    pub fn new() -> Self {
+        Self {
+            record: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for JsonLines<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for JsonLines<T> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for JsonLines<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JsonLines")
+    }
+}
+
+impl<T: DeserializeOwned> RecordParser for JsonLines<T> {
+    type Record = T;
+
+    fn parse(&self, line: &[u8]) -> Result<T, ParseFailure> {
+        Ok(serde_json::from_slice(line)?)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = Fields;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Fields, A::Error> {
+                let mut fields = Fields::default();
+
+                while let Some((key, value)) = map.next_entry::<String, serde_json::Value>()? {
+                    match value {
+                        serde_json::Value::String(value) => fields.push(key, value),
+                        value => fields.push(key, value.to_string()),
+                    }
+                }
+
+                Ok(fields)
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+impl RecordTime for serde_json::Value {}
+
+/// Top level keys of an object. Strings are returned as is, other values as JSON text.
+impl RecordFields for serde_json::Value {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        match self.get(name)? {
+            serde_json::Value::String(value) => Some(Cow::Borrowed(value)),
+            value => Some(Cow::Owned(value.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_keep_document_order() {
+        let fields = JsonLines::<Fields>::new()
+            .parse(br#"{"msg": "hi \"there\"", "n": 1, "ok": true, "tags": ["a"], "none": null}"#)
+            .unwrap();
+        assert_eq!(
+            fields.iter().collect::<Vec<_>>(),
+            [
+                ("msg", r#"hi "there""#),
+                ("n", "1"),
+                ("ok", "true"),
+                ("tags", r#"["a"]"#),
+                ("none", "null"),
+            ]
+        );
+    }
+
+    #[test]
+    fn values_expose_top_level_fields() {
+        let value = JsonLines::<serde_json::Value>::new()
+            .parse(br#"{"msg": "hi", "nested": {"a": 1}}"#)
+            .unwrap();
+        assert_eq!(value.field("msg").as_deref(), Some("hi"));
+        assert_eq!(value.field("nested").as_deref(), Some(r#"{"a":1}"#));
+        assert_eq!(value.field("missing"), None);
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let parser = JsonLines::<serde_json::Value>::new();
+        // A bad escape, a truncated line, trailing data and invalid UTF-8.
+        for line in [
+            &br#"{"msg": "a\qb"}"#[..],
+            br#"{"msg": "trunc"#,
+            br#"{"a": 1} {"b": 2}"#,
+            b"\"\xff\"",
+        ] {
+            assert!(parser.parse(line).is_err(), "{:?}", line);
+        }
+
+        // Only objects make fields.
+        assert!(JsonLines::<Fields>::new().parse(b"[1, 2]").is_err());
+    }
+}
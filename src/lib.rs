@@ -1,42 +1,195 @@
+#[cfg(feature = "chrono")]
+mod access;
+mod broadcast;
+mod dedup;
+#[cfg(feature = "regex")]
+mod extract;
+mod file;
+mod filter;
+mod glob_watcher;
+mod group;
+#[cfg(feature = "serde_json")]
+mod json;
+mod limit;
+mod logfmt;
+#[cfg(feature = "chrono")]
+mod merge;
+mod multiplex;
+mod newest;
+#[cfg(feature = "process")]
+mod process;
+mod records;
+#[cfg(feature = "redact")]
+mod redact;
+mod restart;
+mod rotation;
+#[cfg(feature = "rt")]
+mod rt;
+mod sender;
+mod source;
+#[cfg(feature = "chrono")]
+mod syslog;
+#[cfg(feature = "chrono")]
+mod timestamp;
+
+#[cfg(feature = "chrono")]
+pub use access::{AccessLogEntry, CombinedLog, CommonLog};
+pub use broadcast::LogSubscriber;
+pub use dedup::{Dedup, Deduplicated, Repeated};
+#[cfg(feature = "regex")]
+pub use extract::{FieldType, FieldValue, RegexParser, TypedFields};
+pub use file::Compression;
+pub use filter::{LineFilter, Matcher};
+pub use glob_watcher::{GlobWatcher, GlobWatcherBuilder};
+pub use group::{LogWatcherGroup, LogWatcherGroupBuilder, WatchOptions};
+#[cfg(feature = "serde_json")]
+pub use json::JsonLines;
+pub use limit::{Overflow, RateLimit};
+pub use logfmt::Logfmt;
+#[cfg(feature = "chrono")]
+pub use merge::Merge;
+pub use multiplex::FileError;
+#[cfg(feature = "process")]
+pub use process::{
+    OutputStream, ProcessMessage, ProcessReceiver, ProcessSource, ProcessSourceBuilder,
+};
+pub use records::{
+    Fields, ParseError, ParseFailure, RecordFields, RecordParser, RecordTime, Records,
+};
+#[cfg(feature = "redact")]
+pub use redact::{Detector, Redactor, Replacement};
+pub use restart::{Backoff, RestartPolicy};
+pub use rotation::RotationScheme;
+#[cfg(feature = "rt")]
+pub use rt::LogWatcherHandle;
+pub use sender::TaggedMessage;
+pub use source::{LogSource, SourceFuture};
+#[cfg(feature = "chrono")]
+pub use syslog::{Facility, Rfc3164, Rfc5424, Severity, StructuredData, SyslogMessage};
+#[cfg(feature = "chrono")]
+pub use timestamp::{Event, Metadata, TimestampExtractor, TimestampFormat, Timestamped, Timezone};
+
 use std::{
+    collections::HashSet,
     error::Error,
+    fmt,
     future::Future,
     io::SeekFrom,
     path::{Path, PathBuf},
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Weak},
     time::Duration,
 };
 
 use tokio::{
-    fs::File,
     io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader},
-    sync::mpsc::{
-        error::{SendError, TryRecvError},
-        Receiver, Sender,
+    sync::{
+        mpsc::{
+            error::{SendError, TryRecvError, TrySendError},
+            Receiver, Sender,
+        },
+        watch,
     },
-    time::sleep,
+    time::{sleep, Instant},
 };
 
-#[derive(Debug, Clone, Copy)]
+use broadcast::Broadcast;
+use file::LogFile;
+use filter::Filtered;
+use limit::Limited;
+use newest::{FollowNewest, FOLLOW_INTERVAL};
+#[cfg(feature = "redact")]
+use redact::Redacted;
+use restart::{OnRestart, RestartTracker};
+use sender::LogSender;
+use source::BoxedSource;
+
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogReaderMode {
     ReadToEnd,
     NextLine,
 }
 
+/// What the watcher follows when the file is rotated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FollowMode {
+    /// Re-opens the path once the file is replaced or removed, like `tail -F`.
+    #[default]
+    Name,
+    /// Keeps reading the file first opened even after it is renamed or unlinked, like
+    /// `tail -f`. Signals still switch files.
+    Descriptor,
+}
+
 #[derive(Debug)]
 struct LogBufReader {
-    file: BufReader<File>,
-    sender: Arc<Sender<Vec<u8>>>,
+    file: BufReader<LogFile>,
+    sender: Arc<LogSender>,
     path: PathBuf,
     last_ctime: u64,
+    options: ReadOptions,
+    /// How far a regular file was delivered, `None` for anything else.
+    position: Option<ReadPosition>,
+    /// Data a full channel turned away, sent again before anything else is read.
+    pending: Option<Vec<u8>>,
+}
+
+/// Error of a step of [`DetachedLogWatcher`], with the reader it failed on when it
+/// failed reading, so a file followed by descriptor can be restarted without reopening it.
+#[derive(Debug)]
+struct StepError {
+    error: std::io::Error,
+    reader: Option<LogBufReader>,
+}
+
+impl From<std::io::Error> for StepError {
+    fn from(error: std::io::Error) -> Self {
+        Self {
+            error,
+            reader: None,
+        }
+    }
+}
+
+/// Offset up to which a file was delivered, along with the identity of the file, so a
+/// restarted read can pick up where it left off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ReadPosition {
+    identity: (u64, u64),
+    offset: u64,
+}
+
+/// How the files of a loop are opened and framed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ReadOptions {
     mode: LogReaderMode,
+    compression: Compression,
+    follow: FollowMode,
 }
 
 pub struct LogWatcherBuilder {
     path: PathBuf,
     mode: LogReaderMode,
     skip_to_end: bool,
+    broadcast: Option<usize>,
+    replay: usize,
+    restart_policy: RestartPolicy,
+    on_restart: Option<OnRestart>,
+    follow_newest: Option<FollowNewest>,
+    follow_symlink: bool,
+    catch_up: Option<RotationScheme>,
+    compression: Compression,
+    follow: FollowMode,
+    source: Option<Box<dyn LogSource>>,
+    filter: Option<LineFilter>,
+    rate_limit: Option<RateLimit>,
+    #[cfg(feature = "redact")]
+    redactor: Option<Redactor>,
+    #[cfg(feature = "tokio-util")]
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl LogWatcherBuilder {
@@ -51,31 +204,353 @@ impl LogWatcherBuilder {
         }
     }
 
+    /// Stops the spawned loop once `token` is cancelled, so the watcher can share the
+    /// shutdown path of the rest of the service.
+    #[cfg(feature = "tokio-util")]
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation_token: Some(token),
+            ..self
+        }
+    }
+
+    /// Delivers data through a broadcast channel of `capacity` messages so several
+    /// consumers can [`LogWatcher::subscribe`] to the same file. A capacity of 0 is
+    /// taken as 1, a broadcast channel cannot hold less.
+    pub fn broadcast(self, capacity: usize) -> Self {
+        Self {
+            broadcast: Some(capacity.max(1)),
+            ..self
+        }
+    }
+
+    /// Keeps the last `len` messages around for [`LogWatcher::subscribe_with_replay`].
+    /// Enables broadcasting if it was not already.
+    pub fn replay(self, len: usize) -> Self {
+        Self {
+            broadcast: self.broadcast.or(Some(4096)),
+            replay: len,
+            ..self
+        }
+    }
+
+    /// Keeps the loop alive through read errors other than a missing file by reloading
+    /// the file instead of returning. A file with the same device and inode is resumed
+    /// from where it was left, a rotated one is read from its start. Defaults to
+    /// [`RestartPolicy::Never`].
+    pub fn restart_policy(self, restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            ..self
+        }
+    }
+
+    /// Called from the loop with the error and the delay each time it restarts.
+    pub fn on_restart(
+        self,
+        on_restart: impl Fn(&LogWatcherError, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            on_restart: Some(OnRestart(Arc::new(on_restart))),
+            ..self
+        }
+    }
+
+    /// Treats the watched path as a directory and follows its newest file whose name
+    /// matches `pattern`, e.g. `app-*.log` for date-stamped files. When a newer file
+    /// shows up the current one is drained before switching, like
+    /// [`LogWatcherSignal::Swap`]. A file switched away from is never followed again,
+    /// even if it is written to later.
+    pub fn follow_newest(self, pattern: &str) -> Result<Self, glob::PatternError> {
+        Ok(Self {
+            follow_newest: Some(FollowNewest {
+                pattern: glob::Pattern::new(pattern)?,
+            }),
+            ..self
+        })
+    }
+
+    /// Resolves the watched path on each poll when it is a symlink. Retargeting the link
+    /// is handled like a rotation: the old target is drained, then the new one is read
+    /// from its start.
+    pub fn follow_symlink(self, follow_symlink: bool) -> Self {
+        Self {
+            follow_symlink,
+            ..self
+        }
+    }
+
+    /// Before tailing, reads the rotated copies of the watched file named after `scheme`
+    /// oldest first, then the live file from its start, all into the same stream.
+    /// Overrides `skip_to_end`.
+    pub fn catch_up_rotated(self, scheme: RotationScheme) -> Self {
+        Self {
+            catch_up: Some(scheme),
+            ..self
+        }
+    }
+
+    /// How the watched file is decoded. [`Compression::Auto`], the default, recognizes
+    /// compressed files by their first bytes. A compressed file is read once to its end,
+    /// after which the state becomes [`LogWatcherState::Eof`] and the stream ends.
+    pub fn compression(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    /// Defaults to [`FollowMode::Name`].
+    pub fn follow_mode(self, follow: FollowMode) -> Self {
+        Self { follow, ..self }
+    }
+
+    /// Only delivers the lines `filter` keeps. Lines are dropped in the spawned loop, so
+    /// they take no room in the channel and wake nobody up.
+    pub fn filter(self, filter: LineFilter) -> Self {
+        Self {
+            filter: Some(filter),
+            ..self
+        }
+    }
+
+    /// Limits the lines delivered, after the filter if any. Lines over the limit are
+    /// dropped or sampled in the spawned loop, or the loop waits before reading more.
+    pub fn rate_limit(self, rate_limit: RateLimit) -> Self {
+        Self {
+            rate_limit: Some(rate_limit),
+            ..self
+        }
+    }
+
+    /// Replaces secrets in the delivered lines, inside the spawned loop. The filter, if
+    /// any, sees the lines before they are redacted.
+    #[cfg(feature = "redact")]
+    pub fn redact(self, redactor: Redactor) -> Self {
+        Self {
+            redactor: Some(redactor),
+            ..self
+        }
+    }
+
     pub fn build(self) -> LogWatcher {
-        let (sender, receiver) = tokio::sync::mpsc::channel(4096);
+        let (sender, receiver) = match self.broadcast {
+            Some(capacity) => {
+                let broadcast = LogSender::Broadcast(Broadcast::new(capacity, self.replay));
+                let subscriber = broadcast.subscribe(false).unwrap();
+                (broadcast, ReceiverKind::Broadcast(subscriber))
+            }
+            None => {
+                let (sender, receiver) = tokio::sync::mpsc::channel(4096);
+                (LogSender::Channel(sender), ReceiverKind::Channel(receiver))
+            }
+        };
+        #[cfg(feature = "redact")]
+        let sender = match self.redactor {
+            Some(redactor) => LogSender::Redacted(Box::new(Redacted::new(redactor, sender))),
+            None => sender,
+        };
+        let sender = match self.rate_limit {
+            Some(limit) => LogSender::Limited(Box::new(Limited::new(limit, sender))),
+            None => sender,
+        };
+        let sender = match self.filter {
+            Some(filter) => LogSender::Filtered(Box::new(Filtered::new(filter, sender))),
+            None => sender,
+        };
         let (signal_tx, signal_rx) = tokio::sync::mpsc::channel(4096);
+        let (shutdown_tx, _) = watch::channel(false);
+        let (state_tx, state_rx) = watch::channel(LogWatcherState::Initializing);
+
+        let sender = Arc::new(sender);
+        let shutdown_tx = Arc::new(shutdown_tx);
+        let from_source = self.source.is_some();
+        let broadcast = self.broadcast.is_some();
 
         LogWatcher {
-            receiver,
-            sender: Arc::new(sender),
-            path: self.path,
-            signal_rx: Some(signal_rx).into(),
-            signal_tx,
-            mode: self.mode,
-            skip_to_end: self.skip_to_end,
+            receiver: LogReceiver { receiver },
+            controller: LogWatcherController {
+                config: LoopConfig {
+                    sender: Arc::downgrade(&sender),
+                    path: self.path,
+                    loop_channels: Arc::new(
+                        Some(LoopChannels {
+                            sender: Some(sender),
+                            source: self.source.map(BoxedSource::new),
+                            signal_rx,
+                            state_tx,
+                            position: None,
+                        })
+                        .into(),
+                    ),
+                    shutdown: Arc::downgrade(&shutdown_tx),
+                    mode: self.mode,
+                    skip_to_end: self.skip_to_end,
+                    restart_policy: self.restart_policy,
+                    on_restart: self.on_restart,
+                    follow_newest: self.follow_newest,
+                    follow_symlink: self.follow_symlink,
+                    catch_up: self.catch_up,
+                    compression: self.compression,
+                    follow: self.follow,
+                    from_source,
+                    broadcast,
+                    #[cfg(feature = "tokio-util")]
+                    cancellation_token: self.cancellation_token,
+                },
+                signal_tx,
+                shutdown_tx,
+                state_rx,
+            },
         }
     }
 }
 
+/// Dropping the watcher stops the spawned loop: the data channel and the shutdown
+/// channel both close, and the loop exits on the next await point instead of failing
+/// its next send.
 #[derive(Debug)]
 pub struct LogWatcher {
-    receiver: Receiver<Vec<u8>>,
-    sender: Arc<Sender<Vec<u8>>>,
-    path: PathBuf,
+    receiver: LogReceiver,
+    controller: LogWatcherController,
+}
+
+/// Control half of a [`LogWatcher`]: spawning, signals, shutdown and state.
+///
+/// Clones share the same loop. The loop stops once every controller has been dropped.
+#[derive(Debug, Clone)]
+pub struct LogWatcherController {
+    config: LoopConfig,
     signal_tx: Sender<LogWatcherSignal>,
-    signal_rx: std::sync::Mutex<Option<Receiver<LogWatcherSignal>>>,
+    shutdown_tx: Arc<watch::Sender<bool>>,
+    state_rx: watch::Receiver<LogWatcherState>,
+}
+
+/// Everything needed to (re)spawn the loop. Holds no strong reference to the controller
+/// channels, so a restarting task does not keep the loop alive by itself.
+#[derive(Debug, Clone)]
+struct LoopConfig {
+    sender: Weak<LogSender>,
+    path: PathBuf,
+    loop_channels: Arc<std::sync::Mutex<Option<LoopChannels>>>,
+    shutdown: Weak<watch::Sender<bool>>,
     mode: LogReaderMode,
     skip_to_end: bool,
+    restart_policy: RestartPolicy,
+    on_restart: Option<OnRestart>,
+    follow_newest: Option<FollowNewest>,
+    follow_symlink: bool,
+    catch_up: Option<RotationScheme>,
+    compression: Compression,
+    follow: FollowMode,
+    from_source: bool,
+    broadcast: bool,
+    #[cfg(feature = "tokio-util")]
+    cancellation_token: Option<CancellationToken>,
+}
+
+/// Channel ends owned by the spawned loop while it runs. The data sender is dropped
+/// once a finite file has been read to its end, which ends the stream. A source is
+/// taken by the first loop for good. `position` is where the last loop was reading, for
+/// a restarted one to resume from.
+#[derive(Debug)]
+struct LoopChannels {
+    sender: Option<Arc<LogSender>>,
+    source: Option<BoxedSource>,
+    signal_rx: Receiver<LogWatcherSignal>,
+    state_tx: watch::Sender<LogWatcherState>,
+    position: Option<ReadPosition>,
+}
+
+/// Reports `Closed` and hands the channels back however the loop ends, including errors
+/// and the future being dropped, so the watcher can be spawned again. After `Eof` the
+/// channels come back without their data sender.
+struct LoopGuard {
+    channels: Option<LoopChannels>,
+    slot: Arc<std::sync::Mutex<Option<LoopChannels>>>,
+    eof: bool,
+}
+
+impl LoopGuard {
+    fn channels(&mut self) -> &mut LoopChannels {
+        self.channels.as_mut().unwrap()
+    }
+
+    fn set_eof(&mut self) {
+        self.eof = true;
+    }
+}
+
+impl Drop for LoopGuard {
+    fn drop(&mut self) {
+        if let Some(mut channels) = self.channels.take() {
+            if self.eof {
+                channels.sender = None;
+            }
+            channels.state_tx.send_replace(match channels.sender {
+                Some(_) => LogWatcherState::Closed,
+                None => LogWatcherState::Eof,
+            });
+            *self.slot.lock().unwrap() = Some(channels);
+        }
+    }
+}
+
+/// Data half of a [`LogWatcher`]. The loop stops once it has been dropped, unless the
+/// watcher broadcasts, in which case it is just one more subscriber.
+#[derive(Debug)]
+pub struct LogReceiver {
+    receiver: ReceiverKind,
+}
+
+#[derive(Debug)]
+enum ReceiverKind {
+    Channel(Receiver<Vec<u8>>),
+    Broadcast(LogSubscriber),
+}
+
+/// Last state reported by the spawned loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogWatcherState {
+    Initializing,
+    Waiting,
+    Reading,
+    Missing,
+    Reloading,
+    Closed,
+    /// A finite source, such as a compressed file, was read to its end.
+    Eof,
+}
+
+#[derive(Debug)]
+struct Shutdown {
+    rx: watch::Receiver<bool>,
+    #[cfg(feature = "tokio-util")]
+    token: Option<CancellationToken>,
+}
+
+impl Shutdown {
+    async fn requested(&mut self) {
+        #[cfg(feature = "tokio-util")]
+        if let Some(token) = self.token.clone() {
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = Self::watch_requested(&mut self.rx) => {}
+            }
+            return;
+        }
+
+        Self::watch_requested(&mut self.rx).await
+    }
+
+    async fn watch_requested(rx: &mut watch::Receiver<bool>) {
+        // A dropped sender means the watcher itself was dropped.
+        while !*rx.borrow_and_update() {
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -84,8 +559,10 @@ enum DetachedLogWatcher {
     Waiting(LogBufReader),
     Reading(LogBufReader),
     Missing(LogBufReader),
-    Reloading((PathBuf, Arc<Sender<Vec<u8>>>, LogReaderMode)),
+    /// Carries the position to resume from when the file turns out to be the same one.
+    Reloading((PathBuf, Arc<LogSender>, ReadOptions, Option<ReadPosition>)),
     Closed,
+    Eof,
 }
 #[derive(Debug)]
 pub enum LogWatcherSignal {
@@ -94,84 +571,366 @@ pub enum LogWatcherSignal {
     Swap(PathBuf),
 }
 
-type BoxedError = Box<dyn Error + 'static + Send + Sync>;
-type BoxedResult<T> = Result<T, BoxedError>;
-type SpawnFnResult = Pin<Box<dyn Future<Output = BoxedResult<()>> + Send + Sync>>;
+/// Error that ended the spawned loop.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LogWatcherError {
+    Io(std::io::Error),
+    /// The task running the loop was aborted.
+    #[cfg(feature = "rt")]
+    Aborted,
+    /// The task running the loop panicked.
+    #[cfg(feature = "rt")]
+    Panicked,
+}
+
+impl fmt::Display for LogWatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogWatcherError::Io(err) => write!(f, "log watcher io error: {}", err),
+            #[cfg(feature = "rt")]
+            LogWatcherError::Aborted => write!(f, "log watcher task was aborted"),
+            #[cfg(feature = "rt")]
+            LogWatcherError::Panicked => write!(f, "log watcher task panicked"),
+        }
+    }
+}
+
+impl Error for LogWatcherError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LogWatcherError::Io(err) => Some(err),
+            #[cfg(feature = "rt")]
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LogWatcherError {
+    fn from(err: std::io::Error) -> Self {
+        LogWatcherError::Io(err)
+    }
+}
+
+type SpawnFnResult = Pin<Box<dyn Future<Output = Result<(), LogWatcherError>> + Send + Sync>>;
 
 impl LogWatcher {
+    /// `file_path` may also be a named pipe, a character device such as `/dev/kmsg`, or
+    /// `-` for the standard input of the process. These are read as a stream, without
+    /// seeking or rotation, and standard input ends the stream once it is closed.
     pub fn builder(file_path: impl Into<PathBuf>) -> LogWatcherBuilder {
         LogWatcherBuilder {
             path: file_path.into(),
             mode: LogReaderMode::ReadToEnd,
             skip_to_end: true,
+            broadcast: None,
+            replay: 0,
+            restart_policy: RestartPolicy::Never,
+            on_restart: None,
+            follow_newest: None,
+            follow_symlink: false,
+            catch_up: None,
+            compression: Compression::Auto,
+            follow: FollowMode::Name,
+            source: None,
+            filter: None,
+            rate_limit: None,
+            #[cfg(feature = "redact")]
+            redactor: None,
+            #[cfg(feature = "tokio-util")]
+            cancellation_token: None,
         }
     }
 
-    pub fn set_mode(self, mode: LogReaderMode) -> Self {
-        Self { mode, ..self }
+    /// Reads from `source` instead of a file, with `name` standing in for the path. The
+    /// builder options about paths, such as rotation and following, do not apply.
+    ///
+    /// A source is read by the first spawned loop only. An error from the source ends
+    /// the loop instead of going through the restart policy.
+    pub fn from_source(
+        name: impl Into<PathBuf>,
+        source: impl LogSource + 'static,
+    ) -> LogWatcherBuilder {
+        LogWatcherBuilder {
+            source: Some(Box::new(source)),
+            ..Self::builder(name)
+        }
+    }
+
+    pub fn set_mode(mut self, mode: LogReaderMode) -> Self {
+        self.controller.config.mode = mode;
+        self
+    }
+
+    /// Splits the watcher so data can be read in one task while another one sends signals.
+    pub fn split(self) -> (LogWatcherController, LogReceiver) {
+        (self.controller, self.receiver)
+    }
+
+    pub fn controller(&self) -> &LogWatcherController {
+        &self.controller
     }
 
     pub async fn send_signal(
         &self,
         signal: LogWatcherSignal,
     ) -> Result<(), SendError<LogWatcherSignal>> {
-        self.signal_tx.send(signal).await
+        self.controller.send_signal(signal).await
     }
 
     pub async fn read_message(&mut self) -> Option<Vec<u8>> {
-        self.receiver.recv().await
+        self.receiver.read_message().await
     }
 
     pub fn try_read_message(&mut self) -> Result<Vec<u8>, TryRecvError> {
-        self.receiver.try_recv()
+        self.receiver.try_read_message()
+    }
+
+    pub fn state(&self) -> LogWatcherState {
+        self.controller.state()
+    }
+
+    /// Adds a consumer that receives every message read from now on. Returns `None`
+    /// unless the watcher was built with [`LogWatcherBuilder::broadcast`].
+    pub fn subscribe(&self) -> Option<LogSubscriber> {
+        self.controller.subscribe()
+    }
+
+    /// Like [`LogWatcher::subscribe`], but first yields the messages kept by
+    /// [`LogWatcherBuilder::replay`].
+    pub fn subscribe_with_replay(&self) -> Option<LogSubscriber> {
+        self.controller.subscribe_with_replay()
+    }
+
+    /// Stops the spawned loop and waits for it to exit. Unlike `LogWatcherSignal::Close`
+    /// the current file is not drained first.
+    pub async fn shutdown(self) {
+        self.controller.shutdown().await
+    }
+
+    pub fn spawn(&self) -> SpawnFnResult {
+        self.controller.spawn()
+    }
+
+    /// See [`LogWatcherController::start`].
+    #[cfg(feature = "rt")]
+    pub fn start(&self) -> LogWatcherHandle {
+        self.controller.start()
+    }
+
+    /// See [`LogWatcherController::start_with_restart`].
+    #[cfg(feature = "rt")]
+    pub fn start_with_restart(&self, backoff: Backoff) -> LogWatcherHandle {
+        self.controller.start_with_restart(backoff)
+    }
+}
+
+impl LogReceiver {
+    pub async fn read_message(&mut self) -> Option<Vec<u8>> {
+        match &mut self.receiver {
+            ReceiverKind::Channel(receiver) => receiver.recv().await,
+            ReceiverKind::Broadcast(subscriber) => subscriber.recv().await,
+        }
+    }
+
+    pub fn try_read_message(&mut self) -> Result<Vec<u8>, TryRecvError> {
+        match &mut self.receiver {
+            ReceiverKind::Channel(receiver) => receiver.try_recv(),
+            ReceiverKind::Broadcast(subscriber) => subscriber.try_recv(),
+        }
+    }
+}
+
+impl LogWatcherController {
+    pub async fn send_signal(
+        &self,
+        signal: LogWatcherSignal,
+    ) -> Result<(), SendError<LogWatcherSignal>> {
+        self.signal_tx.send(signal).await
+    }
+
+    pub fn state(&self) -> LogWatcherState {
+        *self.state_rx.borrow()
+    }
+
+    /// See [`LogWatcher::subscribe`].
+    pub fn subscribe(&self) -> Option<LogSubscriber> {
+        self.subscribe_inner(false)
+    }
+
+    /// See [`LogWatcher::subscribe_with_replay`].
+    pub fn subscribe_with_replay(&self) -> Option<LogSubscriber> {
+        self.subscribe_inner(true)
+    }
+
+    fn subscribe_inner(&self, with_replay: bool) -> Option<LogSubscriber> {
+        match self.config.sender.upgrade() {
+            Some(sender) => sender.subscribe(with_replay),
+            // The stream already ended.
+            None if self.config.broadcast => Some(LogSubscriber::ended()),
+            None => None,
+        }
+    }
+
+    /// Waits until the loop reports a state different from the last one seen by this
+    /// controller. Returns `None` once the loop has exited.
+    pub async fn state_changed(&mut self) -> Option<LogWatcherState> {
+        self.state_rx.changed().await.ok()?;
+        Some(*self.state_rx.borrow_and_update())
+    }
+
+    /// Stops the spawned loop and waits for it to exit. Unlike `LogWatcherSignal::Close`
+    /// the current file is not drained first.
+    ///
+    /// Shutting down is final: a loop spawned afterwards, including one respawned by
+    /// `start_with_restart`, exits right away.
+    pub async fn shutdown(&self) {
+        self.shutdown_tx.send_replace(true);
+        self.shutdown_tx.closed().await;
     }
 
+    /// Returns the future running the reading loop. It must be driven by the caller.
+    ///
+    /// The watcher can be spawned again once a previous loop has exited, unless it was
+    /// [shut down](LogWatcherController::shutdown).
+    ///
+    /// # Panics
+    ///
+    /// If a previously spawned loop is still running.
     pub fn spawn(&self) -> SpawnFnResult {
-        let sender = self.sender.clone();
+        self.config.spawn(false)
+    }
+}
+
+impl LoopConfig {
+    /// `restarted` loops skip `skip_to_end` and go straight to reloading the file, from
+    /// where the previous loop was if it is the same file.
+    fn spawn(&self, restarted: bool) -> SpawnFnResult {
         let path = self.path.clone();
 
-        let signal_rx = self.signal_rx.lock().unwrap().take();
+        let shutdown_tx = match self.shutdown.upgrade() {
+            Some(shutdown_tx) => shutdown_tx,
+            // Every controller is gone, there is nobody left to run for.
+            None => return Box::pin(async { Ok(()) }),
+        };
 
-        if signal_rx.is_none() {
+        let loop_channels = self.loop_channels.lock().unwrap().take();
+
+        if loop_channels.is_none() {
             panic!("Log watcher spanwed twice {:?}", self);
         };
 
-        let mut signal_rx = signal_rx.unwrap();
+        let mut guard = LoopGuard {
+            channels: loop_channels,
+            slot: self.loop_channels.clone(),
+            eof: false,
+        };
 
-        let mode = self.mode.clone();
-        let skip_to_end = self.skip_to_end;
+        let sender = match &guard.channels().sender {
+            Some(sender) => sender.clone(),
+            // A finite file was already read to its end.
+            None => return Box::pin(async { Ok(()) }),
+        };
+
+        let source = guard.channels().source.take();
+        let resume = guard.channels().position;
+        let from_source = self.from_source;
+        if from_source && source.is_none() {
+            // The source went away with a previous loop.
+            return Box::pin(async { Ok(()) });
+        }
+
+        let options = ReadOptions {
+            mode: self.mode,
+            compression: self.compression,
+            follow: self.follow,
+        };
+        let skip_to_end = self.skip_to_end && self.catch_up.is_none();
+        let catch_up = self.catch_up;
+        let mut restarts = RestartTracker::new(self.restart_policy, self.on_restart.clone());
+        let follow_newest = self.follow_newest.clone();
+        let follow_symlink = self.follow_symlink;
+        let mut shutdown = Shutdown {
+            rx: shutdown_tx.subscribe(),
+            #[cfg(feature = "tokio-util")]
+            token: self.cancellation_token.clone(),
+        };
 
         let future: SpawnFnResult = Box::pin(async move {
-            let mut detached = match File::open(&path).await {
-                Ok(file) => {
-                    if skip_to_end {
-                        DetachedLogWatcher::Initializing(LogBufReader {
-                            file: BufReader::new(file),
-                            sender: sender.clone(),
-                            path: path.clone(),
-                            last_ctime: get_c_time(&path).await.unwrap(),
-                            mode,
-                        })
-                    } else {
-                        DetachedLogWatcher::Waiting(LogBufReader {
-                            file: BufReader::new(file),
-                            sender: sender.clone(),
-                            path: path.clone(),
-                            last_ctime: get_c_time(&path).await.unwrap(),
-                            mode,
-                        })
-                    }
+            // In follow mode `path` is the directory and `current` the file being read.
+            // Until a matching file exists the directory itself is "reloaded", which
+            // never succeeds since it is not a file.
+            let mut current = path.clone();
+            let mut last_follow_check = Instant::now();
+            // Files switched away from, never followed again.
+            let mut followed = HashSet::new();
+            if let Some(follow_newest) = &follow_newest {
+                if let Some(newest) = follow_newest.newest(&path, &followed).await {
+                    current = newest;
                 }
-                Err(err) => match err.kind() {
-                    std::io::ErrorKind::NotFound => {
-                        DetachedLogWatcher::Reloading((path.clone(), sender.clone(), mode))
+            }
+            // Target of `current` when it was last opened, `None` until it is known.
+            let mut symlink_target = match follow_symlink {
+                true => tokio::fs::canonicalize(&current).await.ok(),
+                false => None,
+            };
+
+            let caught_up = match catch_up {
+                Some(scheme) if !restarted && !from_source => tokio::select! {
+                    biased;
+                    _ = shutdown.requested() => return Ok(()),
+                    caught_up = catch_up_rotated(scheme, &current, sender.clone(), options) => {
+                        caught_up
                     }
-                    _ => Err(err)?,
                 },
+                _ => Ok(()),
+            };
+
+            let opened = if let Some(source) = source {
+                Ok(DetachedLogWatcher::from_source(
+                    current.clone(),
+                    sender.clone(),
+                    options,
+                    skip_to_end,
+                    source,
+                ))
+            } else if restarted {
+                Ok(DetachedLogWatcher::Reloading((
+                    current.clone(),
+                    sender.clone(),
+                    options,
+                    resume,
+                )))
+            } else {
+                match caught_up {
+                    Ok(()) => {
+                        DetachedLogWatcher::open(
+                            current.clone(),
+                            sender.clone(),
+                            options,
+                            skip_to_end,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                }
+            };
+
+            let mut detached = match opened {
+                Ok(detached) => detached,
+                Err(err) => {
+                    let delay = restarts.restart(err)?;
+                    tokio::select! {
+                        biased;
+                        _ = shutdown.requested() => return Ok(()),
+                        _ = sleep(delay) => {}
+                    }
+                    DetachedLogWatcher::Reloading((current.clone(), sender.clone(), options, None))
+                }
             };
 
             loop {
-                match signal_rx.try_recv() {
+                match guard.channels().signal_rx.try_recv() {
                     Ok(LogWatcherSignal::Close) => {
                         detached.close().await;
                     }
@@ -179,6 +938,8 @@ impl LogWatcher {
                         detached.reload().await;
                     }
                     Ok(LogWatcherSignal::Swap(path)) => {
+                        current = path.clone();
+                        symlink_target = None;
                         detached.swap(path).await;
                     }
                     Err(err) => {
@@ -188,21 +949,119 @@ impl LogWatcher {
                     }
                 }
 
+                if let Some(follow_newest) = &follow_newest {
+                    if last_follow_check.elapsed() >= FOLLOW_INTERVAL {
+                        last_follow_check = Instant::now();
+
+                        match follow_newest.newest(&path, &followed).await {
+                            Some(newest) if newest != current => {
+                                detached.drain().await;
+                                detached.swap(newest.clone()).await;
+                                followed.insert(std::mem::replace(&mut current, newest));
+                                symlink_target = None;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                if follow_symlink {
+                    let target = tokio::fs::canonicalize(&current).await.ok();
+
+                    if target.is_some() && target != symlink_target {
+                        if symlink_target.is_some() {
+                            detached.drain().await;
+                            detached.swap(current.clone()).await;
+                        }
+                        symlink_target = target;
+                    }
+                }
+
+                sender.report();
+
+                guard.channels().state_tx.send_if_modified(|state| {
+                    let previous = *state;
+                    *state = detached.state();
+                    previous != *state
+                });
+
                 match detached {
                     DetachedLogWatcher::Closed => {
+                        sender.flush();
+                        break;
+                    }
+                    DetachedLogWatcher::Eof => {
+                        sender.flush();
+                        guard.set_eof();
                         break;
                     }
                     _ => {
+                        let position = detached.position();
+                        guard.channels().position = position;
+                        let next = tokio::select! {
+                            biased;
+                            _ = shutdown.requested() => break,
+                            _ = sender.closed() => break,
+                            next = detached.next() => next,
+                        };
+
                         detached =
-                            match detached.next().await {
+                            match next {
                                 Ok(next) => next,
-                                Err(err) => match err.kind() {
+                                Err(StepError { error: err, reader }) => match err.kind() {
+                                    // The receiving side went away mid-read, nothing left to do.
+                                    std::io::ErrorKind::NotConnected if sender.is_closed() => break,
+                                    // A source has no path to be reloaded from.
+                                    _ if from_source => return Err(err.into()),
                                     std::io::ErrorKind::NotFound => DetachedLogWatcher::Reloading(
-                                        (path.clone(), sender.clone(), mode),
+                                        (current.clone(), sender.clone(), options, None),
                                     ),
-                                    _ => Err(err)?,
+                                    _ => {
+                                        let delay = restarts.restart(err)?;
+                                        tokio::select! {
+                                            biased;
+                                            _ = shutdown.requested() => break,
+                                            _ = sleep(delay) => {}
+                                        }
+                                        match reader {
+                                            // The path may lead to another file by now, the
+                                            // one followed is resumed on its open handle.
+                                            Some(mut inner)
+                                                if options.follow == FollowMode::Descriptor
+                                                    && inner.file.get_ref().is_regular() =>
+                                            {
+                                                inner.resume(position).await?;
+                                                DetachedLogWatcher::Waiting(inner)
+                                            }
+                                            // The same file is resumed instead of read again.
+                                            _ => DetachedLogWatcher::Reloading((
+                                                current.clone(),
+                                                sender.clone(),
+                                                options,
+                                                position,
+                                            )),
+                                        }
+                                    }
                                 },
                             };
+
+                        // A full channel is waited out, what it turned away is sent first.
+                        if detached.is_blocked() {
+                            tokio::select! {
+                                biased;
+                                _ = shutdown.requested() => break,
+                                _ = sender.ready() => {}
+                            }
+                        }
+
+                        // A blocking rate limit holds reading back until it has room.
+                        if let Some(delay) = sender.throttle() {
+                            tokio::select! {
+                                biased;
+                                _ = shutdown.requested() => break,
+                                _ = sleep(delay) => {}
+                            }
+                        }
                     }
                 }
             }
@@ -213,8 +1072,150 @@ impl LogWatcher {
     }
 }
 
+/// Reads every rotated copy of `path` to its end, oldest first, held back by a blocking
+/// rate limit like the file itself.
+async fn catch_up_rotated(
+    scheme: RotationScheme,
+    path: &Path,
+    sender: Arc<LogSender>,
+    options: ReadOptions,
+) -> Result<(), std::io::Error> {
+    for rotated in scheme.discover(path).await {
+        let file = match LogFile::open(&rotated, Compression::Auto).await {
+            Ok(file) => file,
+            // Rotated away again since it was listed.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+
+        let mut inner = LogBufReader {
+            position: read_position(&file).await,
+            file: BufReader::new(file),
+            sender: sender.clone(),
+            last_ctime: get_c_time(&rotated).await?,
+            path: rotated,
+            options,
+            pending: None,
+        };
+
+        while inner.read_next().await? > 0 {
+            while !inner.send_pending().await? {
+                sender.ready().await;
+            }
+            if let Some(delay) = sender.throttle() {
+                sleep(delay).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl DetachedLogWatcher {
-    pub async fn next(self) -> Result<Self, std::io::Error> {
+    fn from_source(
+        path: PathBuf,
+        sender: Arc<LogSender>,
+        options: ReadOptions,
+        skip_to_end: bool,
+        source: BoxedSource,
+    ) -> Self {
+        let inner = LogBufReader {
+            file: BufReader::new(LogFile::Source(source)),
+            sender,
+            path,
+            last_ctime: 0,
+            options,
+            position: None,
+            pending: None,
+        };
+
+        if skip_to_end {
+            DetachedLogWatcher::Initializing(inner)
+        } else {
+            DetachedLogWatcher::Waiting(inner)
+        }
+    }
+
+    /// Opens `path` for a new loop. A missing file is waited for instead of failing.
+    async fn open(
+        path: PathBuf,
+        sender: Arc<LogSender>,
+        options: ReadOptions,
+        skip_to_end: bool,
+    ) -> Result<Self, std::io::Error> {
+        match LogFile::open(&path, options.compression).await {
+            Ok(file) => {
+                // Only regular files can seek, the others are read from where they are.
+                let skip_to_end = skip_to_end && file.is_regular();
+                let inner = LogBufReader {
+                    last_ctime: file_c_time(&file, &path).await?,
+                    position: read_position(&file).await,
+                    file: BufReader::new(file),
+                    sender,
+                    path,
+                    options,
+                    pending: None,
+                };
+
+                if skip_to_end {
+                    Ok(DetachedLogWatcher::Initializing(inner))
+                } else {
+                    Ok(DetachedLogWatcher::Waiting(inner))
+                }
+            }
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => {
+                    Ok(DetachedLogWatcher::Reloading((path, sender, options, None)))
+                }
+                _ => Err(err),
+            },
+        }
+    }
+
+    fn state(&self) -> LogWatcherState {
+        match self {
+            DetachedLogWatcher::Initializing(_) => LogWatcherState::Initializing,
+            DetachedLogWatcher::Waiting(_) => LogWatcherState::Waiting,
+            DetachedLogWatcher::Reading(_) => LogWatcherState::Reading,
+            DetachedLogWatcher::Missing(_) => LogWatcherState::Missing,
+            DetachedLogWatcher::Reloading(_) => LogWatcherState::Reloading,
+            DetachedLogWatcher::Closed => LogWatcherState::Closed,
+            DetachedLogWatcher::Eof => LogWatcherState::Eof,
+        }
+    }
+
+    /// Where a restart should resume reading from.
+    fn position(&self) -> Option<ReadPosition> {
+        match self {
+            DetachedLogWatcher::Initializing(_) => None,
+            DetachedLogWatcher::Waiting(inner)
+            | DetachedLogWatcher::Reading(inner)
+            | DetachedLogWatcher::Missing(inner) => inner.position,
+            DetachedLogWatcher::Reloading((_, _, _, resume)) => *resume,
+            DetachedLogWatcher::Closed | DetachedLogWatcher::Eof => None,
+        }
+    }
+
+    /// Whether a full channel turned data away, the loop waits for room before going on.
+    fn is_blocked(&self) -> bool {
+        match self {
+            DetachedLogWatcher::Waiting(inner)
+            | DetachedLogWatcher::Reading(inner)
+            | DetachedLogWatcher::Missing(inner) => inner.pending.is_some(),
+            _ => false,
+        }
+    }
+
+    pub async fn next(mut self) -> Result<Self, StepError> {
+        if let DetachedLogWatcher::Waiting(inner)
+        | DetachedLogWatcher::Reading(inner)
+        | DetachedLogWatcher::Missing(inner) = &mut self
+        {
+            if !inner.send_pending().await? {
+                return Ok(self);
+            }
+        }
+
         match self {
             DetachedLogWatcher::Initializing(mut inner) => {
                 inner.skip_file().await?;
@@ -222,24 +1223,36 @@ impl DetachedLogWatcher {
             }
             DetachedLogWatcher::Waiting(mut inner) => match inner.read_next().await {
                 Ok(size) if size > 4096 => Ok(DetachedLogWatcher::Reading(inner)),
+                Ok(0) if inner.file.get_ref().is_finite() => {
+                    inner.flush_buffer();
+                    Ok(DetachedLogWatcher::Eof)
+                }
                 Ok(size) => {
-                    if size == 0 {
+                    if size == 0 && inner.source_rotated().await? {
+                        inner.reopen_source().await?;
+                    }
+
+                    // Only regular files are replaced by rotation.
+                    if size == 0
+                        && inner.options.follow == FollowMode::Name
+                        && inner.file.get_ref().is_regular()
+                    {
                         let curr_ctime = get_c_time(&inner.path).await?;
 
                         if curr_ctime > inner.last_ctime {
                             return Ok(DetachedLogWatcher::Missing(inner));
                         }
-
-                        sleep(Duration::from_millis(200)).await;
-                        Ok(DetachedLogWatcher::Waiting(inner))
-                    } else {
-                        sleep(Duration::from_millis(200)).await;
-                        Ok(DetachedLogWatcher::Waiting(inner))
                     }
+
+                    sleep(Duration::from_millis(200)).await;
+                    Ok(DetachedLogWatcher::Waiting(inner))
                 }
                 Err(err) => match err.kind() {
                     std::io::ErrorKind::NotFound => Ok(DetachedLogWatcher::Missing(inner)),
-                    _ => Err(err),
+                    _ => Err(StepError {
+                        error: err,
+                        reader: Some(inner),
+                    }),
                 },
             },
             DetachedLogWatcher::Reading(mut inner) => match inner.read_next().await {
@@ -247,20 +1260,28 @@ impl DetachedLogWatcher {
                 Ok(_) => Ok(DetachedLogWatcher::Reading(inner)),
                 Err(err) => match err.kind() {
                     std::io::ErrorKind::NotFound => Ok(DetachedLogWatcher::Missing(inner)),
-                    _ => Err(err),
+                    _ => Err(StepError {
+                        error: err,
+                        reader: Some(inner),
+                    }),
                 },
             },
+            DetachedLogWatcher::Missing(mut inner) if inner.file.get_ref().is_source() => {
+                inner.reopen_source().await?;
+                Ok(DetachedLogWatcher::Waiting(inner))
+            }
             DetachedLogWatcher::Missing(inner) => {
-                inner.sender.try_send(inner.file.buffer().to_vec()).ok();
+                inner.flush_buffer();
                 Ok(DetachedLogWatcher::Reloading((
                     inner.path,
                     inner.sender,
-                    inner.mode,
+                    inner.options,
+                    None,
                 )))
             }
-            DetachedLogWatcher::Reloading((path, sender, mode)) => {
+            DetachedLogWatcher::Reloading((path, sender, options, resume)) => {
                 let file_exists = match tokio::fs::metadata(&path).await {
-                    Ok(meta) => Ok(meta.is_file()),
+                    Ok(meta) => Ok(file::is_readable(&meta)),
                     Err(err) => match err.kind() {
                         std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied => {
                             Ok(false)
@@ -269,22 +1290,29 @@ impl DetachedLogWatcher {
                     },
                 }?;
 
-                if file_exists {
-                    let new_inner = LogBufReader {
-                        file: BufReader::new(File::open(&path).await?),
+                if file_exists || path == Path::new(file::STDIN_PATH) {
+                    let file = LogFile::open(&path, options.compression).await?;
+                    let mut new_inner = LogBufReader {
+                        last_ctime: file_c_time(&file, &path).await.unwrap(),
+                        position: read_position(&file).await,
+                        file: BufReader::new(file),
                         path: path.clone(),
                         sender,
-                        last_ctime: get_c_time(&path).await.unwrap(),
-                        mode,
+                        options,
+                        pending: None,
                     };
+                    new_inner.resume(resume).await?;
 
                     Ok(DetachedLogWatcher::Waiting(new_inner))
                 } else {
                     sleep(Duration::from_secs(1)).await;
-                    Ok(DetachedLogWatcher::Reloading((path, sender, mode)))
+                    Ok(DetachedLogWatcher::Reloading((
+                        path, sender, options, resume,
+                    )))
                 }
             }
             DetachedLogWatcher::Closed => Ok(DetachedLogWatcher::Closed),
+            DetachedLogWatcher::Eof => Ok(DetachedLogWatcher::Eof),
         }
     }
 
@@ -297,7 +1325,9 @@ impl DetachedLogWatcher {
                 inner.read_next().await.ok();
                 *self = DetachedLogWatcher::Closed
             }
-            DetachedLogWatcher::Reloading(_) => *self = DetachedLogWatcher::Closed,
+            DetachedLogWatcher::Reloading(_) | DetachedLogWatcher::Eof => {
+                *self = DetachedLogWatcher::Closed
+            }
             DetachedLogWatcher::Closed => {}
         }
     }
@@ -308,18 +1338,46 @@ impl DetachedLogWatcher {
             | DetachedLogWatcher::Waiting(inner)
             | DetachedLogWatcher::Reading(inner)
             | DetachedLogWatcher::Missing(inner) => {
+                // A source may not have anything to read yet, it just starts over.
+                if inner.file.get_ref().is_source() {
+                    inner.reopen_source().await.ok();
+                    return;
+                }
+
                 let result = inner.read_next().await.unwrap_or(0);
 
                 if result == 0 {
-                    inner.sender.try_send(inner.file.buffer().to_vec()).ok();
+                    inner.flush_buffer();
                 }
                 *self = DetachedLogWatcher::Reloading((
                     inner.path.clone(),
                     inner.sender.clone(),
-                    inner.mode,
+                    inner.options,
+                    None,
                 ));
             }
-            DetachedLogWatcher::Reloading(_) | DetachedLogWatcher::Closed => {}
+            DetachedLogWatcher::Reloading(_)
+            | DetachedLogWatcher::Closed
+            | DetachedLogWatcher::Eof => {}
+        }
+    }
+
+    /// Reads whatever is left in the current file, so nothing is lost by moving on.
+    pub async fn drain(&mut self) {
+        match self {
+            DetachedLogWatcher::Waiting(inner)
+            | DetachedLogWatcher::Reading(inner)
+            | DetachedLogWatcher::Missing(inner) => {
+                while let Ok(size) = inner.read_next().await {
+                    if size == 0 {
+                        break;
+                    }
+                }
+            }
+            DetachedLogWatcher::Initializing(_)
+            | DetachedLogWatcher::Reloading(_)
+            | DetachedLogWatcher::Closed
+            | DetachedLogWatcher::Eof => {}
         }
     }
 
@@ -332,21 +1390,37 @@ impl DetachedLogWatcher {
                 let result = inner.read_next().await.unwrap_or(0);
 
                 if result == 0 {
-                    inner.sender.try_send(inner.file.buffer().to_vec()).ok();
+                    inner.flush_buffer();
                 }
-                *self = DetachedLogWatcher::Reloading((path, inner.sender.clone(), inner.mode));
+                *self = DetachedLogWatcher::Reloading((
+                    path,
+                    inner.sender.clone(),
+                    inner.options,
+                    None,
+                ));
             }
-            DetachedLogWatcher::Reloading((_old_path, sender, mode)) => {
-                *self = DetachedLogWatcher::Reloading((path, sender.clone(), mode.clone()));
+            DetachedLogWatcher::Reloading((_old_path, sender, options, _)) => {
+                *self = DetachedLogWatcher::Reloading((path, sender.clone(), *options, None));
             }
-            DetachedLogWatcher::Closed => {}
+            DetachedLogWatcher::Closed | DetachedLogWatcher::Eof => {}
         }
     }
 }
 
 impl LogBufReader {
+    /// Sends whatever is left in the read buffer before the file is let go.
+    fn flush_buffer(&self) {
+        if !self.file.buffer().is_empty() {
+            self.sender.try_send(self.file.buffer().to_vec()).ok();
+        }
+    }
+
     async fn read_next(&mut self) -> Result<usize, std::io::Error> {
-        match self.mode {
+        if !self.send_pending().await? {
+            return Ok(0);
+        }
+
+        match self.options.mode {
             LogReaderMode::ReadToEnd => self.read_to_end().await,
             LogReaderMode::NextLine => self.read_next_line().await,
         }
@@ -361,48 +1435,34 @@ impl LogBufReader {
 
             match self.file.read_line(&mut buffer).await {
                 Ok(size) if size > 0 => {
-                    if total_size > MAX_SIZE {
-                        return match self.sender.try_send(buffer.into_bytes()) {
-                            Ok(_) => match get_c_time(&self.path).await {
-                                Ok(ctime) => {
-                                    self.last_ctime = ctime;
-                                    Ok(total_size)
-                                }
-                                Err(err) => match err.kind() {
-                                    std::io::ErrorKind::NotFound => Ok(total_size),
-                                    std::io::ErrorKind::UnexpectedEof => Ok(total_size),
-                                    _ => Err(err),
-                                },
+                    // A stream has no end to batch up to, whatever arrived is sent as is.
+                    let drained = self.file.get_ref().is_stream() && self.file.buffer().is_empty();
+
+                    if total_size > MAX_SIZE || drained {
+                        let total_size = buffer.len();
+                        return match self.send(buffer.into_bytes()).await {
+                            Ok(_) => Ok(total_size),
+                            Err(err) => match err.kind() {
+                                std::io::ErrorKind::NotFound => Ok(total_size),
+                                std::io::ErrorKind::UnexpectedEof => Ok(total_size),
+                                _ => Err(err),
                             },
-                            Err(_) => Err(std::io::Error::new(
-                                std::io::ErrorKind::NotConnected,
-                                "failed to send to channel",
-                            )),
                         };
                     } else {
                         continue;
                     };
                 }
                 Ok(_) => {
-                    if buffer.len() == 0 {
+                    if buffer.is_empty() {
                         return Ok(0);
                     }
-                    return match self.sender.try_send(buffer.into_bytes()) {
-                        Ok(_) => match get_c_time(&self.path).await {
-                            Ok(ctime) => {
-                                self.last_ctime = ctime;
-                                Ok(total_size)
-                            }
-                            Err(err) => match err.kind() {
-                                std::io::ErrorKind::NotFound => Ok(total_size),
-                                std::io::ErrorKind::UnexpectedEof => Ok(total_size),
-                                _ => Err(err),
-                            },
+                    return match self.send(buffer.into_bytes()).await {
+                        Ok(_) => Ok(total_size),
+                        Err(err) => match err.kind() {
+                            std::io::ErrorKind::NotFound => Ok(total_size),
+                            std::io::ErrorKind::UnexpectedEof => Ok(total_size),
+                            _ => Err(err),
                         },
-                        Err(_) => Err(std::io::Error::new(
-                            std::io::ErrorKind::NotConnected,
-                            "failed to send to channel",
-                        )),
                     };
                 }
                 Err(err) => {
@@ -418,24 +1478,27 @@ impl LogBufReader {
 
     async fn read_to_end(&mut self) -> Result<usize, std::io::Error> {
         let mut buffer: Vec<u8> = Vec::new();
-        let result: Result<usize, std::io::Error> = self.file.read_to_end(&mut buffer).await;
+        let result: Result<usize, std::io::Error> = if self.file.get_ref().is_stream() {
+            // A stream never ends, take what is available instead.
+            match self.file.fill_buf().await {
+                Ok(available) => {
+                    buffer.extend_from_slice(available);
+                    self.file.consume(buffer.len());
+                    Ok(buffer.len())
+                }
+                Err(err) => Err(err),
+            }
+        } else {
+            self.file.read_to_end(&mut buffer).await
+        };
         match result {
-            Ok(size) if size > 0 => match self.sender.try_send(buffer) {
-                Ok(_) => match get_c_time(&self.path).await {
-                    Ok(ctime) => {
-                        self.last_ctime = ctime;
-                        Ok(size)
-                    }
-                    Err(err) => match err.kind() {
-                        std::io::ErrorKind::NotFound => Ok(0),
-                        std::io::ErrorKind::UnexpectedEof => Ok(0),
-                        _ => Err(err),
-                    },
+            Ok(size) if size > 0 => match self.send(buffer).await {
+                Ok(_) => Ok(size),
+                Err(err) => match err.kind() {
+                    std::io::ErrorKind::NotFound => Ok(0),
+                    std::io::ErrorKind::UnexpectedEof => Ok(0),
+                    _ => Err(err),
                 },
-                Err(_) => Err(std::io::Error::new(
-                    std::io::ErrorKind::NotConnected,
-                    "failed to send to channel",
-                )),
             },
             Ok(size) => Ok(size),
             Err(err) => match err.kind() {
@@ -446,10 +1509,106 @@ impl LogBufReader {
         }
     }
 
+    /// Sends `data` and moves past it. Data a full channel turns away is kept to be sent
+    /// again, so a file is never reopened or read twice to wait for room.
+    async fn send(&mut self, data: Vec<u8>) -> Result<(), std::io::Error> {
+        let size = data.len();
+
+        match self.sender.try_send(data) {
+            Ok(_) => {
+                self.last_ctime = self.advance(size).await?;
+                Ok(())
+            }
+            Err(TrySendError::Full(data)) => {
+                self.pending = Some(data);
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "failed to send to channel",
+            )),
+        }
+    }
+
+    /// Sends the data a full channel turned away, if any. `false` while there is still
+    /// no room for it.
+    async fn send_pending(&mut self) -> Result<bool, std::io::Error> {
+        if let Some(data) = self.pending.take() {
+            match self.send(data).await {
+                Ok(_) => {}
+                Err(err) => match err.kind() {
+                    std::io::ErrorKind::NotFound | std::io::ErrorKind::UnexpectedEof => {}
+                    _ => return Err(err),
+                },
+            }
+        }
+        Ok(self.pending.is_none())
+    }
+
+    /// Moves the position past `size` delivered bytes and returns the ctime of the file.
+    /// Only regular files are looked up by path, the others keep the ctime they had.
+    async fn advance(&mut self, size: usize) -> Result<u64, std::io::Error> {
+        if let Some(position) = &mut self.position {
+            position.offset += size as u64;
+        }
+        if !self.file.get_ref().is_regular() {
+            return Ok(self.last_ctime);
+        }
+        get_c_time(&self.path).await
+    }
+
     async fn skip_file(&mut self) -> Result<(), std::io::Error> {
-        self.file.seek(SeekFrom::End(0)).await?;
+        match self.file.get_mut() {
+            LogFile::Source(source) => source.source.skip_to_end().await?,
+            _ => {
+                let end = self.file.seek(SeekFrom::End(0)).await?;
+                if let Some(position) = &mut self.position {
+                    position.offset = end;
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Seeks back to `resume` when the file is still the one it was taken from. A file
+    /// that was truncated below it is read from its start.
+    async fn resume(&mut self, resume: Option<ReadPosition>) -> Result<(), std::io::Error> {
+        let (Some(resume), Some(mut position)) = (resume, self.position) else {
+            return Ok(());
+        };
+        if resume.identity != position.identity {
+            return Ok(());
+        }
+
+        let len = self.file.seek(SeekFrom::End(0)).await?;
+        let offset = if resume.offset <= len {
+            resume.offset
+        } else {
+            0
+        };
+        position.offset = self.file.seek(SeekFrom::Start(offset)).await?;
+        self.position = Some(position);
+        Ok(())
+    }
+
+    async fn source_rotated(&mut self) -> Result<bool, std::io::Error> {
+        match self.file.get_mut() {
+            LogFile::Source(source) => source.rotated().await,
+            _ => Ok(false),
+        }
+    }
+
+    /// Sends what is buffered from the source, then reopens it in place.
+    async fn reopen_source(&mut self) -> Result<(), std::io::Error> {
+        self.flush_buffer();
+        let buffered = self.file.buffer().len();
+        self.file.consume(buffered);
+
+        match self.file.get_mut() {
+            LogFile::Source(source) => source.source.reopen().await,
+            _ => Ok(()),
+        }
+    }
 }
 
 #[cfg(windows)]
@@ -467,3 +1626,36 @@ async fn get_c_time(path: &Path) -> Result<u64, std::io::Error> {
     let meta = tokio::fs::metadata(path).await?;
     Ok(meta.ctime() as u64)
 }
+
+/// Start of a freshly opened `file`, when it is a regular file.
+async fn read_position(file: &LogFile) -> Option<ReadPosition> {
+    match file {
+        LogFile::Plain(file) => file.metadata().await.ok().map(|meta| ReadPosition {
+            identity: file_identity(&meta),
+            offset: 0,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+fn file_identity(meta: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::windows::prelude::MetadataExt;
+
+    (0, meta.creation_time())
+}
+
+#[cfg(unix)]
+fn file_identity(meta: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::prelude::MetadataExt;
+
+    (meta.dev(), meta.ino())
+}
+
+/// ctime of a freshly opened `file`. Stdin and sources have no path to look at.
+async fn file_c_time(file: &LogFile, path: &Path) -> Result<u64, std::io::Error> {
+    match file {
+        LogFile::Stdin(_) | LogFile::Source(_) => Ok(0),
+        _ => get_c_time(path).await,
+    }
+}
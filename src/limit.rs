@@ -0,0 +1,217 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::{sync::mpsc::error::TrySendError, time::Instant};
+
+use crate::sender::{rejected, LogSender};
+
+/// What a [`RateLimit`] does with the lines over its rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Drop,
+    /// Keeps one line out of every `n` over the rate and drops the others. `n` must be
+    /// at least 2, keeping one line out of one would not limit anything.
+    Sample(u32),
+    /// Delivers every line, and holds the loop back from reading until the rate allows
+    /// it again.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Lines,
+    Bytes,
+}
+
+/// A token bucket limiting the lines a watcher delivers.
+///
+/// The bucket holds up to `burst` lines or bytes, one second worth of the rate by
+/// default. Unless the overflow is [`Overflow::Block`], lines dropped since the last
+/// report are announced every `report_interval` by a `<n> lines suppressed by rate
+/// limit` line of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    unit: Unit,
+    rate: f64,
+    burst: f64,
+    overflow: Overflow,
+    report_interval: Duration,
+}
+
+impl RateLimit {
+    /// Panics if `lines` is zero.
+    pub fn lines_per_second(lines: u32) -> Self {
+        Self::new(Unit::Lines, lines.into())
+    }
+
+    /// Panics if `bytes` is zero.
+    pub fn bytes_per_second(bytes: u32) -> Self {
+        Self::new(Unit::Bytes, bytes.into())
+    }
+
+    fn new(unit: Unit, rate: f64) -> Self {
+        assert!(rate > 0.0, "rate limit of zero");
+        Self {
+            unit,
+            rate,
+            burst: rate,
+            overflow: Overflow::Drop,
+            report_interval: Duration::from_secs(10),
+        }
+    }
+
+    pub fn burst(self, burst: u32) -> Self {
+        Self {
+            burst: burst.into(),
+            ..self
+        }
+    }
+
+    /// Defaults to [`Overflow::Drop`]. Panics on [`Overflow::Sample`] of less than 2.
+    pub fn overflow(self, overflow: Overflow) -> Self {
+        assert!(
+            !matches!(overflow, Overflow::Sample(0 | 1)),
+            "sampling one line out of fewer than 2"
+        );
+        Self { overflow, ..self }
+    }
+
+    /// Defaults to 10 seconds.
+    pub fn report_interval(self, report_interval: Duration) -> Self {
+        Self {
+            report_interval,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    /// Lines over the rate since the last sampled one.
+    over: u32,
+    suppressed: u64,
+    reported: Instant,
+    /// Whether the unterminated line ending the last message was kept.
+    continued: Option<bool>,
+}
+
+/// A sender delivering lines within a rate limit.
+#[derive(Debug)]
+pub(crate) struct Limited {
+    limit: RateLimit,
+    pub(crate) sender: LogSender,
+    bucket: Mutex<Bucket>,
+}
+
+impl Limited {
+    pub(crate) fn new(limit: RateLimit, sender: LogSender) -> Self {
+        let now = Instant::now();
+
+        Self {
+            limit,
+            sender,
+            bucket: Mutex::new(Bucket {
+                tokens: limit.burst,
+                refilled: now,
+                over: 0,
+                suppressed: 0,
+                reported: now,
+                continued: None,
+            }),
+        }
+    }
+
+    /// Sends the lines of `data` within the limit. Data the channel turns away is given
+    /// back and leaves the bucket as it was, it is counted once it is sent again.
+    pub(crate) fn send(&self, data: Vec<u8>) -> Result<(), TrySendError<Vec<u8>>> {
+        let mut current = self.bucket.lock().unwrap();
+        self.refill(&mut current);
+        let mut bucket = current.clone();
+        let mut kept = Vec::new();
+
+        for line in data.split_inclusive(|&byte| byte == b'\n') {
+            let cost = match self.limit.unit {
+                Unit::Lines => 1.0,
+                Unit::Bytes => line.len() as f64,
+            };
+
+            let keep = match bucket.continued.take() {
+                // The rest of a line goes with its start, but still costs its bytes.
+                Some(keep) => {
+                    if keep && self.limit.unit == Unit::Bytes {
+                        bucket.tokens -= cost;
+                    }
+                    keep
+                }
+                // A line larger than the whole bucket passes once the bucket is full.
+                None if bucket.tokens >= cost.min(self.limit.burst)
+                    || self.limit.overflow == Overflow::Block =>
+                {
+                    bucket.tokens -= cost;
+                    true
+                }
+                None => {
+                    bucket.over += 1;
+                    let keep = match self.limit.overflow {
+                        Overflow::Sample(n) if bucket.over >= n => {
+                            bucket.over = 0;
+                            true
+                        }
+                        _ => false,
+                    };
+                    if !keep {
+                        bucket.suppressed += 1;
+                    }
+                    keep
+                }
+            };
+
+            if keep {
+                kept.extend_from_slice(line);
+            }
+            if !line.ends_with(b"\n") {
+                bucket.continued = Some(keep);
+            }
+        }
+
+        if !kept.is_empty() {
+            self.sender
+                .try_send(kept)
+                .map_err(|err| rejected(err, data))?;
+        }
+        *current = bucket;
+        Ok(())
+    }
+
+    /// How long the loop should wait before reading more, when blocking.
+    pub(crate) fn throttle(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+
+        (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / self.limit.rate))
+    }
+
+    /// Sends the count of suppressed lines once the report interval has passed, and not
+    /// in the middle of a line.
+    pub(crate) fn report(&self) {
+        let mut bucket = self.bucket.lock().unwrap();
+        let due = bucket.reported.elapsed() >= self.limit.report_interval;
+
+        if bucket.suppressed > 0 && due && bucket.continued.is_none() {
+            let record = format!("{} lines suppressed by rate limit\n", bucket.suppressed);
+            if self.sender.try_send(record.into_bytes()).is_ok() {
+                bucket.suppressed = 0;
+                bucket.reported = Instant::now();
+            }
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.refilled).as_secs_f64() * self.limit.rate;
+
+        bucket.tokens = (bucket.tokens + refill).min(self.limit.burst);
+        bucket.refilled = now;
+    }
+}
@@ -0,0 +1,120 @@
+use crate::records::{Fields, ParseFailure, RecordParser};
+
+/// Parses logfmt lines such as `level=info msg="started server" port=8080` into
+/// [`Fields`], in the order they appear.
+///
+/// Quoted values understand the `\"`, `\\`, `\n`, `\r` and `\t` escapes. A key without
+/// `=` is a flag and gets an empty value.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Logfmt;
+
+impl RecordParser for Logfmt {
+    type Record = Fields;
+
+    fn parse(&self, line: &[u8]) -> Result<Fields, ParseFailure> {
+        let line = std::str::from_utf8(line)?;
+        let mut fields = Fields::default();
+        let mut chars = line.char_indices().peekable();
+
+        loop {
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+            let start = match chars.peek() {
+                Some(&(start, _)) => start,
+                None => return Ok(fields),
+            };
+
+            let mut key = String::new();
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c != '=' && !c.is_whitespace()) {
+                if c == '"' {
+                    return Err(format!("quote in key at byte {}", start).into());
+                }
+                key.push(c);
+            }
+            if key.is_empty() {
+                return Err(format!("missing key at byte {}", start).into());
+            }
+
+            if chars.next_if(|&(_, c)| c == '=').is_none() {
+                fields.push(key, "");
+                continue;
+            }
+
+            let mut value = String::new();
+            if chars.next_if(|&(_, c)| c == '"').is_some() {
+                let unterminated = || format!("unterminated quote for key {:?}", key);
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 'r')) => value.push('\r'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, c @ ('"' | '\\'))) => value.push(c),
+                            Some((_, c)) => {
+                                value.push('\\');
+                                value.push(c);
+                            }
+                            None => return Err(unterminated().into()),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(unterminated().into()),
+                    }
+                }
+            } else {
+                while let Some((_, c)) = chars.next_if(|&(_, c)| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+
+            fields.push(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Fields, ParseFailure> {
+        Logfmt.parse(line.as_bytes())
+    }
+
+    #[test]
+    fn quoted_values_and_escapes() {
+        let fields = parse(r#"msg="a \"quoted\" word" path="C:\\tmp" tab="a\tb""#).unwrap();
+        assert_eq!(fields.get("msg"), Some(r#"a "quoted" word"#));
+        assert_eq!(fields.get("path"), Some(r"C:\tmp"));
+        assert_eq!(fields.get("tab"), Some("a\tb"));
+
+        assert_eq!(parse(r#"key="a\"b""#).unwrap().get("key"), Some(r#"a"b"#));
+        // Unknown escapes are kept as written.
+        assert_eq!(parse(r#"key="a\qb""#).unwrap().get("key"), Some(r"a\qb"));
+    }
+
+    #[test]
+    fn empty_values_and_bare_keys() {
+        let fields = parse("key= flag other=1").unwrap();
+        let fields = fields.iter().collect::<Vec<_>>();
+        assert_eq!(fields, [("key", ""), ("flag", ""), ("other", "1")]);
+
+        assert_eq!(parse("flag").unwrap().get("flag"), Some(""));
+        assert_eq!(parse(r#"key="""#).unwrap().get("key"), Some(""));
+        assert!(parse("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_lines_are_rejected() {
+        for line in [r#"key="abc"#, r#"key="abc\"#, r#"a=1 key="abc\""#] {
+            let error = parse(line).unwrap_err().to_string();
+            assert!(error.starts_with("unterminated quote"), "{line}: {error}");
+        }
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        assert!(parse("=value").is_err());
+        assert!(parse(r#"a"b=1"#).is_err());
+        assert!(Logfmt.parse(b"key=\xff").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset};
+use futures_util::future::select_all;
+use tokio::time::{sleep_until, Instant};
+
+use crate::{
+    records::{ParseError, RecordParser, Records},
+    Event,
+};
+
+/// Merges the events of several [`Records`] into one stream ordered by their
+/// timestamps, each tagged with the index of the source it came from.
+///
+/// An event is held back until every open source has reached its timestamp, so a
+/// source that is idle, or lagging by more than `lateness`, would hold everything
+/// back. Events are let out regardless once they waited for `lateness`, so a late one
+/// may then come out of order. Parse errors are passed through as they come.
+#[derive(Debug)]
+pub struct Merge<P: RecordParser> {
+    sources: Vec<Source<P>>,
+    lateness: Duration,
+    pending: BinaryHeap<Pending<P::Record>>,
+    /// Timestamps of the pending events by arrival, with the time they are let out.
+    deadlines: VecDeque<(Instant, DateTime<FixedOffset>)>,
+    /// Latest timestamp of an event let out by its deadline.
+    forced: Option<DateTime<FixedOffset>>,
+    arrivals: u64,
+}
+
+#[derive(Debug)]
+struct Source<P> {
+    records: Records<P>,
+    latest: Option<DateTime<FixedOffset>>,
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct Pending<T> {
+    timestamp: DateTime<FixedOffset>,
+    arrival: u64,
+    source: usize,
+    event: T,
+}
+
+// Reversed so that the heap pops the earliest event, first come first for ties.
+impl<T> Ord for Pending<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.timestamp, other.arrival).cmp(&(self.timestamp, self.arrival))
+    }
+}
+
+impl<T> PartialOrd for Pending<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Pending<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Pending<T> {}
+
+impl<P, R> Merge<P>
+where
+    P: RecordParser<Record = Event<R>>,
+{
+    pub fn new(sources: impl IntoIterator<Item = Records<P>>, lateness: Duration) -> Self {
+        Self {
+            sources: sources
+                .into_iter()
+                .map(|records| Source {
+                    records,
+                    latest: None,
+                    closed: false,
+                })
+                .collect(),
+            lateness,
+            pending: BinaryHeap::new(),
+            deadlines: VecDeque::new(),
+            forced: None,
+            arrivals: 0,
+        }
+    }
+
+    /// Returns `None` once every source is closed and every event was returned.
+    pub async fn next(&mut self) -> Option<Result<(usize, Event<R>), ParseError>> {
+        loop {
+            let now = Instant::now();
+            while let Some(&(deadline, timestamp)) = self.deadlines.front() {
+                if deadline > now {
+                    break;
+                }
+                self.forced = self.forced.max(Some(timestamp));
+                self.deadlines.pop_front();
+            }
+
+            let open = self.sources.iter().filter(|source| !source.closed);
+            let watermark = match open.map(|source| source.latest).min() {
+                Some(watermark) => watermark.max(self.forced),
+                // Every source is closed, nothing can come before what is left.
+                None => {
+                    let pending = self.pending.pop()?;
+                    return Some(Ok((pending.source, pending.event)));
+                }
+            };
+
+            if let Some(pending) = self.pending.peek() {
+                if Some(pending.timestamp) <= watermark {
+                    let pending = self.pending.pop().unwrap();
+                    return Some(Ok((pending.source, pending.event)));
+                }
+            }
+
+            let reads = self
+                .sources
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, source)| !source.closed)
+                .map(|(index, source)| {
+                    Box::pin(async move { (index, source.records.next().await) })
+                });
+            let deadline = self.deadlines.front().map(|&(deadline, _)| deadline);
+
+            let (index, read) = tokio::select! {
+                ((index, read), _, _) = select_all(reads) => (index, read),
+                _ = sleep_until(deadline.unwrap_or(now)), if deadline.is_some() => continue,
+            };
+
+            match read {
+                Some(Ok(event)) => {
+                    let timestamp = event.metadata.timestamp;
+                    let source = &mut self.sources[index];
+                    source.latest = source.latest.max(Some(timestamp));
+
+                    self.pending.push(Pending {
+                        timestamp,
+                        arrival: self.arrivals,
+                        source: index,
+                        event,
+                    });
+                    self.arrivals += 1;
+                    self.deadlines
+                        .push_back((Instant::now() + self.lateness, timestamp));
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => self.sources[index].closed = true,
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use tokio::{sync::mpsc::Sender, time::sleep};
+
+use crate::{
+    restart::RestartTracker, sender::LogSender, DetachedLogWatcher, LogWatcherError, ReadOptions,
+    ReadPosition, RestartPolicy, TaggedMessage,
+};
+
+/// Path and generation of a file, where it was before the step, and the step itself.
+type StepResult = (
+    PathBuf,
+    u64,
+    Option<ReadPosition>,
+    Result<DetachedLogWatcher, std::io::Error>,
+);
+type Step = Pin<Box<dyn Future<Output = StepResult> + Send + Sync>>;
+
+/// Error that stopped one file of a multiplexed watcher, once its restart policy gave
+/// up. The other files are not affected.
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub error: LogWatcherError,
+}
+
+#[derive(Debug)]
+struct FileEntry {
+    generation: u64,
+    sender: Arc<LogSender>,
+    options: ReadOptions,
+    restarts: RestartTracker,
+}
+
+/// Drives the state machines of many files from a single future, tagging everything
+/// they read with their path.
+///
+/// Each file has at most one step in flight. Removing a file lets its current step
+/// finish and then drops the result, so there is no per-file task to cancel. A file that
+/// fails for good stays listed without a step, so a rescan does not pick it up again.
+pub(crate) struct Multiplexer {
+    sender: Sender<TaggedMessage>,
+    files: HashMap<PathBuf, FileEntry>,
+    steps: FuturesUnordered<Step>,
+    generation: u64,
+}
+
+impl Multiplexer {
+    pub(crate) fn new(sender: Sender<TaggedMessage>) -> Self {
+        Self {
+            sender,
+            files: HashMap::new(),
+            steps: FuturesUnordered::new(),
+            generation: 0,
+        }
+    }
+
+    /// Starts watching `path`. Does nothing if it is already watched.
+    pub(crate) fn add(
+        &mut self,
+        path: PathBuf,
+        options: ReadOptions,
+        skip_to_end: bool,
+        restart_policy: RestartPolicy,
+    ) {
+        if self.files.contains_key(&path) {
+            return;
+        }
+
+        self.generation += 1;
+        let generation = self.generation;
+        let sender = Arc::new(LogSender::Tagged(path.clone(), self.sender.clone()));
+
+        self.files.insert(
+            path.clone(),
+            FileEntry {
+                generation,
+                sender: sender.clone(),
+                options,
+                restarts: RestartTracker::new(restart_policy, None),
+            },
+        );
+
+        self.steps.push(Box::pin(async move {
+            let opened = DetachedLogWatcher::open(path.clone(), sender, options, skip_to_end).await;
+            (path, generation, None, opened)
+        }));
+    }
+
+    pub(crate) fn remove(&mut self, path: &Path) -> bool {
+        self.files.remove(path).is_some()
+    }
+
+    pub(crate) fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    pub(crate) fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.files.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Waits for one file to make progress and queues its next step. Returns the error
+    /// of a file that stopped, the others go on. Never resolves while no file is
+    /// watched. Cancel safe.
+    pub(crate) async fn step(&mut self) -> Option<FileError> {
+        let (path, generation, position, result) = match self.steps.next().await {
+            Some(step) => step,
+            None => std::future::pending().await,
+        };
+
+        let entry = match self.files.get_mut(&path) {
+            Some(entry) if entry.generation == generation => entry,
+            // Removed, or removed and added again, while the step was in flight.
+            _ => return None,
+        };
+
+        let step: Step = match result {
+            Ok(DetachedLogWatcher::Closed) => {
+                self.files.remove(&path);
+                return None;
+            }
+            // Finite files stay listed once read, so a rescan does not read them again.
+            Ok(DetachedLogWatcher::Eof) => return None,
+            Ok(detached) => {
+                let position = detached.position();
+                let sender = entry.sender.clone();
+                Box::pin(async move {
+                    // The shared channel is full, the file goes on once it has room.
+                    if detached.is_blocked() {
+                        sender.ready().await;
+                    }
+                    let next = detached.next().await.map_err(|err| err.error);
+                    (path, generation, position, next)
+                })
+            }
+            Err(err) => {
+                let sender = entry.sender.clone();
+                let options = entry.options;
+                let reloading = |path: &PathBuf, position| {
+                    DetachedLogWatcher::Reloading((path.clone(), sender.clone(), options, position))
+                };
+
+                match err.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        let reloading = reloading(&path, None);
+                        Box::pin(async move { (path, generation, None, Ok(reloading)) })
+                    }
+                    // The receiver is gone, the owner of the multiplexer stops on its own.
+                    std::io::ErrorKind::NotConnected if entry.sender.is_closed() => return None,
+                    _ => match entry.restarts.restart(err) {
+                        Ok(delay) => {
+                            let reloading = reloading(&path, position);
+                            Box::pin(async move {
+                                sleep(delay).await;
+                                (path, generation, position, Ok(reloading))
+                            })
+                        }
+                        Err(error) => return Some(FileError { path, error }),
+                    },
+                }
+            }
+        };
+
+        self.steps.push(step);
+        None
+    }
+}
@@ -0,0 +1,55 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use glob::{MatchOptions, Pattern};
+
+/// How often the directory is listed again looking for a newer file.
+pub(crate) const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Follows the most recently modified file of a directory whose name matches `pattern`.
+#[derive(Debug, Clone)]
+pub(crate) struct FollowNewest {
+    pub(crate) pattern: Pattern,
+}
+
+impl FollowNewest {
+    /// Newest matching file in `dir` that is not in `followed`. Ties on modification
+    /// time go to the greatest name, which is the newest one for date-stamped files.
+    ///
+    /// Files followed before are left out, so a late write to one of them does not
+    /// switch back to it.
+    pub(crate) async fn newest(&self, dir: &Path, followed: &HashSet<PathBuf>) -> Option<PathBuf> {
+        let options = MatchOptions {
+            require_literal_leading_dot: true,
+            ..MatchOptions::new()
+        };
+        let mut entries = tokio::fs::read_dir(dir).await.ok()?;
+        let mut newest: Option<(SystemTime, PathBuf)> = None;
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if !self
+                .pattern
+                .matches_with(&entry.file_name().to_string_lossy(), options)
+                || followed.contains(&entry.path())
+            {
+                continue;
+            }
+
+            let meta = match entry.metadata().await {
+                Ok(meta) if meta.is_file() => meta,
+                _ => continue,
+            };
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let candidate = (modified, entry.path());
+
+            if newest.as_ref().is_none_or(|newest| candidate > *newest) {
+                newest = Some(candidate);
+            }
+        }
+
+        newest.map(|(_, path)| path)
+    }
+}
@@ -0,0 +1,438 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    future::Future,
+    pin::Pin,
+    process::{ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    process::{ChildStderr, ChildStdout, Command},
+    time::{sleep, Sleep},
+};
+
+use crate::{
+    restart::RestartTracker, source::SourceFuture, Backoff, LogReceiver, LogSource, RestartPolicy,
+};
+
+/// A line without a newline is handed out once it grows past this, like a batch of
+/// `LogReaderMode::NextLine`.
+const MAX_LINE: usize = 4096 * 16;
+
+/// Output stream of a watched process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    fn index(self) -> usize {
+        match self {
+            OutputStream::Stdout => 0,
+            OutputStream::Stderr => 1,
+        }
+    }
+}
+
+pub struct ProcessSourceBuilder {
+    program: OsString,
+    args: Vec<OsString>,
+    restart_policy: RestartPolicy,
+}
+
+impl ProcessSourceBuilder {
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// What happens once the process exits or fails to start. Defaults to
+    /// [`RestartPolicy::Always`] with the default [`Backoff`]. When the policy gives up
+    /// both sources end, with an error unless the process exited successfully.
+    pub fn restart_policy(self, restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            ..self
+        }
+    }
+
+    /// The stdout and stderr sources of the process, which is started once either of
+    /// them is first read.
+    pub fn build(self) -> (ProcessSource, ProcessSource) {
+        let mut command = Command::new(self.program);
+        command
+            .args(self.args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let supervisor = Arc::new(Mutex::new(Supervisor {
+            command,
+            restarts: RestartTracker::new(self.restart_policy, None),
+            exit: None,
+            delay: None,
+            pipes: [None, None],
+            read: [true, true],
+            wakers: [None, None],
+            status: Status::Running,
+        }));
+
+        let source = |stream| ProcessSource {
+            stream,
+            supervisor: supervisor.clone(),
+            pipe: None,
+            buffer: Vec::new(),
+            complete: 0,
+        };
+        (source(OutputStream::Stdout), source(OutputStream::Stderr))
+    }
+}
+
+/// One output stream of a command such as `journalctl -f`, read with
+/// [`crate::LogWatcher::from_source`] like any other source. Built in pairs by
+/// [`ProcessSource::builder`], so stdout and stderr go through watchers of their own,
+/// each with its framing, filters, limits and signals.
+///
+/// The process is restarted after it exits according to the restart policy, and killed
+/// once both sources are dropped. `Reload` on either watcher restarts it, as soon as the
+/// watcher is not waiting for output from a quiet process. A source only hands out whole
+/// lines while the process runs, so an idle process never splits one. [`ProcessReceiver`]
+/// reads both watchers as one stream. When reading a stream fails its watcher ends with the error, and the
+/// other stream goes on. Dropping a source closes its pipe, and later runs of the
+/// process write that stream to nowhere.
+#[derive(Debug)]
+pub struct ProcessSource {
+    stream: OutputStream,
+    supervisor: Arc<Mutex<Supervisor>>,
+    pipe: Option<Pipe>,
+    /// Data read from the pipe, of which the first `complete` bytes can be handed out.
+    buffer: Vec<u8>,
+    complete: usize,
+}
+
+impl ProcessSource {
+    pub fn builder(program: impl Into<OsString>) -> ProcessSourceBuilder {
+        ProcessSourceBuilder {
+            program: program.into(),
+            args: Vec::new(),
+            restart_policy: RestartPolicy::Always(Backoff::default()),
+        }
+    }
+
+    pub fn stream(&self) -> OutputStream {
+        self.stream
+    }
+
+    /// Marks everything buffered up to the last newline as ready to hand out, or all of
+    /// it once the pipe ended or the line grew too long.
+    fn mark_complete(&mut self) {
+        self.complete = match self.pipe {
+            Some(_) if self.buffer.len() <= MAX_LINE => self
+                .buffer
+                .iter()
+                .rposition(|byte| *byte == b'\n')
+                .map_or(0, |newline| newline + 1),
+            _ => self.buffer.len(),
+        };
+    }
+}
+
+impl AsyncRead for ProcessSource {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = &mut *self;
+
+        loop {
+            if this.complete > 0 {
+                let size = this.complete.min(buf.remaining());
+                buf.put_slice(&this.buffer[..size]);
+                this.buffer.drain(..size);
+                this.complete -= size;
+                return Poll::Ready(Ok(()));
+            }
+
+            let Some(pipe) = &mut this.pipe else {
+                // The pipe of the next run of the process, once it was started.
+                let mut supervisor = this.supervisor.lock().unwrap();
+                supervisor.poll(this.stream, cx);
+                match supervisor.pipes[this.stream.index()].take() {
+                    Some(pipe) => {
+                        this.pipe = Some(pipe);
+                        continue;
+                    }
+                    None => {
+                        return match &supervisor.status {
+                            Status::Running => Poll::Pending,
+                            Status::Ended => Poll::Ready(Ok(())),
+                            Status::Failed(message) => {
+                                Poll::Ready(Err(std::io::Error::other(message.clone())))
+                            }
+                        };
+                    }
+                }
+            };
+
+            let mut chunk = [0; 8192];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            match Pin::new(pipe).poll_read(cx, &mut chunk) {
+                Poll::Ready(Ok(())) if chunk.filled().is_empty() => {
+                    // The process closed the stream, whatever is left is handed out.
+                    this.pipe = None;
+                    this.mark_complete();
+                    if this.complete == 0 {
+                        continue;
+                    }
+                }
+                Poll::Ready(Ok(())) => {
+                    this.buffer.extend_from_slice(chunk.filled());
+                    this.mark_complete();
+                }
+                Poll::Ready(Err(err)) => {
+                    this.pipe = None;
+                    return Poll::Ready(Err(err));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl LogSource for ProcessSource {
+    /// Ends once the restart policy gave up and everything was read.
+    fn is_finite(&self) -> bool {
+        self.pipe.is_none()
+            && self.buffer.is_empty()
+            && self.supervisor.lock().unwrap().ended(self.stream)
+    }
+
+    /// Restarts the process right away, dropping what is left of this stream.
+    fn reopen(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async move {
+            self.pipe = None;
+            self.buffer.clear();
+            self.complete = 0;
+            self.supervisor.lock().unwrap().restart();
+            Ok(())
+        })
+    }
+}
+
+impl Drop for ProcessSource {
+    fn drop(&mut self) {
+        if let Ok(mut supervisor) = self.supervisor.lock() {
+            supervisor.read[self.stream.index()] = false;
+            supervisor.pipes[self.stream.index()] = None;
+        }
+    }
+}
+
+/// Data read from one stream of a process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessMessage {
+    pub stream: OutputStream,
+    pub data: Vec<u8>,
+}
+
+/// Reads the watchers of the stdout and stderr of a process as one stream, each message
+/// tagged with the stream it came from.
+#[derive(Debug)]
+pub struct ProcessReceiver {
+    receivers: [Option<LogReceiver>; 2],
+}
+
+impl ProcessReceiver {
+    pub fn new(stdout: LogReceiver, stderr: LogReceiver) -> Self {
+        Self {
+            receivers: [Some(stdout), Some(stderr)],
+        }
+    }
+
+    /// The next message of either stream, `None` once both watchers are done.
+    pub async fn read_message(&mut self) -> Option<ProcessMessage> {
+        loop {
+            let (stream, data) = match &mut self.receivers {
+                [Some(stdout), Some(stderr)] => tokio::select! {
+                    data = stdout.read_message() => (OutputStream::Stdout, data),
+                    data = stderr.read_message() => (OutputStream::Stderr, data),
+                },
+                [Some(stdout), None] => (OutputStream::Stdout, stdout.read_message().await),
+                [None, Some(stderr)] => (OutputStream::Stderr, stderr.read_message().await),
+                [None, None] => return None,
+            };
+
+            match data {
+                Some(data) => return Some(ProcessMessage { stream, data }),
+                None => self.receivers[stream.index()] = None,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Pipe {
+    Stdout(ChildStdout),
+    Stderr(ChildStderr),
+}
+
+impl AsyncRead for Pipe {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Pipe::Stdout(stdout) => Pin::new(stdout).poll_read(cx, buf),
+            Pipe::Stderr(stderr) => Pin::new(stderr).poll_read(cx, buf),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Status {
+    Running,
+    /// The restart policy gave up after the process exited successfully.
+    Ended,
+    /// The restart policy gave up on a failure.
+    Failed(String),
+}
+
+/// Resolves once a run of the process exited. Dropping it kills the process.
+type Exit = Pin<Box<dyn Future<Output = std::io::Result<ExitStatus>> + Send>>;
+
+/// The process shared by the two sources, with the pipes of its current run that were
+/// not picked up yet.
+struct Supervisor {
+    command: Command,
+    restarts: RestartTracker,
+    exit: Option<Exit>,
+    /// Backoff before the process is started again, once it exited.
+    delay: Option<Pin<Box<Sleep>>>,
+    pipes: [Option<Pipe>; 2],
+    /// Which streams still have a source reading them.
+    read: [bool; 2],
+    /// Sources waiting for the process to exit or start again.
+    wakers: [Option<Waker>; 2],
+    status: Status,
+}
+
+impl fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("command", &self.command)
+            .field("running", &self.exit.is_some())
+            .field("pipes", &self.pipes)
+            .field("read", &self.read)
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Supervisor {
+    fn ended(&self, stream: OutputStream) -> bool {
+        self.pipes[stream.index()].is_none() && !matches!(self.status, Status::Running)
+    }
+
+    /// Notices the process exited, and starts it again once its backoff ran out. Both wake
+    /// every waiting source, the process itself only wakes the last one to poll it.
+    fn poll(&mut self, stream: OutputStream, cx: &mut Context<'_>) {
+        if self.progress(cx) {
+            self.wakers
+                .iter_mut()
+                .flat_map(Option::take)
+                .for_each(Waker::wake);
+        }
+        self.wakers[stream.index()] = Some(cx.waker().clone());
+    }
+
+    fn progress(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut progressed = false;
+
+        loop {
+            if let Some(exit) = &mut self.exit {
+                match exit.as_mut().poll(cx) {
+                    Poll::Ready(exited) => {
+                        self.exit = None;
+                        self.exited(exited);
+                    }
+                    Poll::Pending => return progressed,
+                }
+            }
+            if !matches!(self.status, Status::Running) {
+                return progressed;
+            }
+            if let Some(delay) = &mut self.delay {
+                match delay.as_mut().poll(cx) {
+                    Poll::Ready(()) => self.delay = None,
+                    Poll::Pending => return progressed,
+                }
+            }
+
+            self.start();
+            progressed = true;
+        }
+    }
+
+    /// Kills the process and starts it again right away, even if the policy gave up.
+    fn restart(&mut self) {
+        self.exit = None;
+        self.delay = None;
+        self.status = Status::Running;
+        self.start();
+        self.wakers
+            .iter_mut()
+            .flat_map(Option::take)
+            .for_each(Waker::wake);
+    }
+
+    fn start(&mut self) {
+        let stdio = |read| match read {
+            true => Stdio::piped(),
+            false => Stdio::null(),
+        };
+        self.command
+            .stdout(stdio(self.read[0]))
+            .stderr(stdio(self.read[1]));
+
+        match self.command.spawn() {
+            Ok(mut child) => {
+                self.pipes = [
+                    child.stdout.take().map(Pipe::Stdout),
+                    child.stderr.take().map(Pipe::Stderr),
+                ];
+                self.exit = Some(Box::pin(async move { child.wait().await }));
+            }
+            Err(err) => self.exited(Err(err)),
+        }
+    }
+
+    fn exited(&mut self, exited: std::io::Result<ExitStatus>) {
+        let (success, err) = match exited {
+            Ok(status) => (
+                status.success(),
+                std::io::Error::other(format!(
+                    "{:?} exited with {}",
+                    self.command.as_std().get_program(),
+                    status
+                )),
+            ),
+            Err(err) => (false, err),
+        };
+
+        match self.restarts.restart(err) {
+            Ok(delay) => self.delay = Some(Box::pin(sleep(delay))),
+            Err(_) if success => self.status = Status::Ended,
+            Err(err) => self.status = Status::Failed(err.to_string()),
+        }
+    }
+}
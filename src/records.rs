@@ -0,0 +1,275 @@
+use std::{borrow::Cow, collections::VecDeque, error::Error, fmt, time::SystemTime};
+
+use tokio::sync::mpsc::{error::TryRecvError, UnboundedSender};
+
+use crate::LogReceiver;
+
+/// Error returned by a [`RecordParser`] for a line it cannot make sense of.
+pub type ParseFailure = Box<dyn Error + Send + Sync>;
+
+/// Turns one line of log output into a record.
+///
+/// Parsers work on whole lines without their line ending, whatever the
+/// [`crate::LogReaderMode`] of the watcher, and are used through
+/// [`LogReceiver::records`].
+pub trait RecordParser {
+    type Record;
+
+    fn parse(&self, line: &[u8]) -> Result<Self::Record, ParseFailure>;
+
+    /// Parses a line that came off the channel at `read_at`, for parsers that fall back
+    /// on the time a line was read. Defaults to [`RecordParser::parse`].
+    fn parse_at(&self, line: &[u8], _read_at: SystemTime) -> Result<Self::Record, ParseFailure> {
+        self.parse(line)
+    }
+
+    /// Attaches the timestamp found by `extractor` to each record.
+    #[cfg(feature = "chrono")]
+    fn timestamped(self, extractor: crate::TimestampExtractor) -> crate::Timestamped<Self>
+    where
+        Self: Sized,
+    {
+        crate::Timestamped::new(self, extractor)
+    }
+}
+
+/// Named fields of a parsed record, as text, for stages that look records up by field.
+pub trait RecordFields {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>>;
+}
+
+/// When a record says it happened, used by [`crate::Deduplicated`] for its window. An
+/// empty `impl` is enough for records without a time.
+pub trait RecordTime {
+    fn record_time(&self) -> Option<SystemTime> {
+        None
+    }
+}
+
+impl<P: RecordParser + ?Sized> RecordParser for Box<P> {
+    type Record = P::Record;
+
+    fn parse(&self, line: &[u8]) -> Result<Self::Record, ParseFailure> {
+        (**self).parse(line)
+    }
+
+    fn parse_at(&self, line: &[u8], read_at: SystemTime) -> Result<Self::Record, ParseFailure> {
+        (**self).parse_at(line, read_at)
+    }
+}
+
+/// Ordered key/value pairs of a structured line, as produced by [`crate::Logfmt`].
+///
+/// Parsers of different formats that produce `Fields` can be swapped behind a
+/// `Box<dyn RecordParser<Record = Fields>>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields(Vec<(String, String)>);
+
+impl Fields {
+    /// Value of the first field named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.0.push((key.into(), value.into()));
+    }
+}
+
+impl RecordFields for Fields {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.get(name).map(Cow::Borrowed)
+    }
+}
+
+impl RecordTime for Fields {}
+
+impl FromIterator<(String, String)> for Fields {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Fields(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Fields {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// A line that could not be parsed, passed through with the reason.
+#[derive(Debug)]
+pub struct ParseError {
+    raw: Vec<u8>,
+    reason: ParseFailure,
+}
+
+impl ParseError {
+    /// The line as it was read, without its line ending.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn into_raw(self) -> Vec<u8> {
+        self.raw
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unparsable log line: {}", self.reason)
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.reason)
+    }
+}
+
+/// Records parsed from the messages of a [`LogReceiver`], one per line.
+///
+/// Lines split across messages are put back together before being parsed, and empty
+/// lines are skipped. A line that fails to parse is yielded as a [`ParseError`], or
+/// sent to the error output set with [`Records::route_errors`], and the stream goes on.
+#[derive(Debug)]
+pub struct Records<P> {
+    receiver: LogReceiver,
+    parser: P,
+    lines: VecDeque<Vec<u8>>,
+    partial: Vec<u8>,
+    /// When the message holding the queued lines came off the channel.
+    read_at: SystemTime,
+    errors: Option<UnboundedSender<ParseError>>,
+}
+
+impl LogReceiver {
+    /// Parses every line read from now on with `parser`.
+    pub fn records<P: RecordParser>(self, parser: P) -> Records<P> {
+        Records {
+            receiver: self,
+            parser,
+            lines: VecDeque::new(),
+            partial: Vec::new(),
+            read_at: SystemTime::now(),
+            errors: None,
+        }
+    }
+}
+
+impl<P: RecordParser> Records<P> {
+    /// Sends the lines that fail to parse to `errors` instead of yielding them. They
+    /// are yielded again once `errors` is closed.
+    pub fn route_errors(self, errors: UnboundedSender<ParseError>) -> Self {
+        Self {
+            errors: Some(errors),
+            ..self
+        }
+    }
+
+    /// Returns `None` once the receiver is closed and every line was parsed.
+    pub async fn next(&mut self) -> Option<Result<P::Record, ParseError>> {
+        while let Some(line) = self.next_line().await {
+            if let Some(result) = self.parse(line) {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+
+    /// Never yields an unterminated line, since more of it may be on its way.
+    pub fn try_next(&mut self) -> Result<Result<P::Record, ParseError>, TryRecvError> {
+        loop {
+            if let Some(line) = self.lines.pop_front() {
+                match self.parse(line) {
+                    Some(result) => return Ok(result),
+                    None => continue,
+                }
+            }
+
+            let data = self.receiver.try_read_message()?;
+            self.push(&data);
+        }
+    }
+
+    /// Gives the receiver back. Lines read but not yet parsed are lost.
+    pub fn into_inner(self) -> LogReceiver {
+        self.receiver
+    }
+
+    /// The next line, without its line ending. `None` once the receiver is closed.
+    pub(crate) async fn next_line(&mut self) -> Option<Vec<u8>> {
+        loop {
+            if let Some(line) = self.lines.pop_front() {
+                return Some(line);
+            }
+
+            match self.receiver.read_message().await {
+                Some(data) => self.push(&data),
+                // An unterminated last line is still a line.
+                None if !self.partial.is_empty() => {
+                    let line = std::mem::take(&mut self.partial);
+                    self.push_line(line);
+                }
+                None => return None,
+            }
+        }
+    }
+
+    /// Lines are only read once the queue is empty, so they all share the read time.
+    fn push(&mut self, data: &[u8]) {
+        self.read_at = SystemTime::now();
+        let mut rest = data;
+
+        while let Some(end) = rest.iter().position(|&byte| byte == b'\n') {
+            self.partial.extend_from_slice(&rest[..end]);
+            let line = std::mem::take(&mut self.partial);
+            self.push_line(line);
+            rest = &rest[end + 1..];
+        }
+
+        self.partial.extend_from_slice(rest);
+    }
+
+    fn push_line(&mut self, mut line: Vec<u8>) {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if !line.is_empty() {
+            self.lines.push_back(line);
+        }
+    }
+
+    /// `None` when the line failed and was routed to the error output.
+    pub(crate) fn parse(&self, line: Vec<u8>) -> Option<Result<P::Record, ParseError>> {
+        let error = match self.parser.parse_at(&line, self.read_at) {
+            Ok(record) => return Some(Ok(record)),
+            Err(reason) => ParseError { raw: line, reason },
+        };
+
+        match &self.errors {
+            Some(errors) => errors.send(error).err().map(|unsent| Err(unsent.0)),
+            None => Some(Err(error)),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::VecDeque, fmt, sync::Arc, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::LogWatcherError;
+
+/// Exponential delay between restarts of a failed loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    factor: u32,
+}
+
+impl Backoff {
+    /// Starts at `initial` and doubles on every attempt, up to `max`.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            factor: 2,
+        }
+    }
+
+    /// Multiplies the delay by `factor` on every attempt. A factor of 1 waits `initial`
+    /// every time.
+    pub fn factor(self, factor: u32) -> Self {
+        Self {
+            factor: factor.max(1),
+            ..self
+        }
+    }
+
+    /// Longest delay, also how long a run must last for the delay to start over.
+    #[cfg(feature = "rt")]
+    pub(crate) fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Delay before restart number `attempt`, counting from 0.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let multiplier = self.factor.saturating_pow(attempt);
+        self.initial.saturating_mul(multiplier).min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
+
+/// What the spawned loop does when reading fails with anything other than a missing
+/// file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Return the error from the loop future.
+    #[default]
+    Never,
+    /// Always reload the file after waiting `Backoff`. The backoff grows with
+    /// consecutive restarts and starts over once the loop ran for longer than its
+    /// maximum delay without failing.
+    Always(Backoff),
+    /// Reload the file at most `max` times within any `window`, then give up.
+    UpTo {
+        max: u32,
+        window: Duration,
+        backoff: Backoff,
+    },
+}
+
+type OnRestartFn = dyn Fn(&LogWatcherError, Duration) + Send + Sync;
+
+/// Called with the error that caused a restart and the delay before it happens.
+#[derive(Clone)]
+pub(crate) struct OnRestart(pub(crate) Arc<OnRestartFn>);
+
+impl fmt::Debug for OnRestart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnRestart")
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RestartTracker {
+    policy: RestartPolicy,
+    on_restart: Option<OnRestart>,
+    restarts: VecDeque<Instant>,
+    attempt: u32,
+}
+
+impl RestartTracker {
+    pub(crate) fn new(policy: RestartPolicy, on_restart: Option<OnRestart>) -> Self {
+        Self {
+            policy,
+            on_restart,
+            restarts: VecDeque::new(),
+            attempt: 0,
+        }
+    }
+
+    /// Returns the delay before restarting after `err`, or `err` itself once the policy
+    /// gives up.
+    pub(crate) fn restart(&mut self, err: std::io::Error) -> Result<Duration, LogWatcherError> {
+        let now = Instant::now();
+        let err = LogWatcherError::from(err);
+
+        let delay = match self.policy {
+            RestartPolicy::Never => return Err(err),
+            RestartPolicy::Always(backoff) => {
+                if let Some(last) = self.restarts.pop_back() {
+                    if now.duration_since(last) > backoff.max {
+                        self.attempt = 0;
+                    }
+                }
+                self.restarts.push_back(now);
+
+                let delay = backoff.delay(self.attempt);
+                self.attempt = self.attempt.saturating_add(1);
+                delay
+            }
+            RestartPolicy::UpTo {
+                max,
+                window,
+                backoff,
+            } => {
+                while let Some(first) = self.restarts.front() {
+                    if now.duration_since(*first) <= window {
+                        break;
+                    }
+                    self.restarts.pop_front();
+                }
+
+                if self.restarts.len() >= max as usize {
+                    return Err(err);
+                }
+
+                let delay = backoff.delay(self.restarts.len() as u32);
+                self.restarts.push_back(now);
+                delay
+            }
+        };
+
+        if let Some(on_restart) = &self.on_restart {
+            (on_restart.0)(&err, delay);
+        }
+
+        Ok(delay)
+    }
+}
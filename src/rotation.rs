@@ -0,0 +1,100 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+/// How rotated copies of a log file are named, used to catch up on them with
+/// [`crate::LogWatcherBuilder::catch_up_rotated`].
+///
+/// A `.gz` or `.zst` extension after the suffix is accepted by both schemes. Compressed
+/// files are only read with the `gzip` or `zstd` feature and skipped otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationScheme {
+    /// `app.log.1`, `app.log.2.gz`, ... The highest number is the oldest.
+    Numeric,
+    /// `app.log-20261017`, `app.log.2026-10-17.gz`, ... The suffix is separated by `.`,
+    /// `-` or `_` and must start with a digit. Suffixes sort oldest first.
+    Date,
+}
+
+impl RotationScheme {
+    /// Rotated copies of `path` found next to it, oldest first.
+    pub(crate) async fn discover(&self, path: &Path) -> Vec<PathBuf> {
+        let (dir, name) = match (path.parent(), path.file_name().and_then(OsStr::to_str)) {
+            (Some(dir), Some(name)) => (dir, name),
+            _ => return Vec::new(),
+        };
+        let list_dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+
+        let mut entries = match tokio::fs::read_dir(list_dir).await {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut numeric: Vec<(u64, PathBuf)> = Vec::new();
+        let mut dated: Vec<(String, PathBuf)> = Vec::new();
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let file_name = entry.file_name();
+            let suffix = match file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix(name))
+            {
+                Some(suffix) => suffix,
+                None => continue,
+            };
+
+            let suffix = if let Some(suffix) = suffix.strip_suffix(".gz") {
+                if !cfg!(feature = "gzip") {
+                    continue;
+                }
+                suffix
+            } else if let Some(suffix) = suffix.strip_suffix(".zst") {
+                if !cfg!(feature = "zstd") {
+                    continue;
+                }
+                suffix
+            } else {
+                suffix
+            };
+
+            let mut chars = suffix.chars();
+            let separator = chars.next();
+            let rest = chars.as_str();
+
+            match self {
+                RotationScheme::Numeric => {
+                    if separator != Some('.') {
+                        continue;
+                    }
+                    if let Ok(number) = rest.parse::<u64>() {
+                        numeric.push((number, dir.join(&file_name)));
+                    }
+                }
+                RotationScheme::Date => {
+                    if !matches!(separator, Some('.' | '-' | '_'))
+                        || !rest.starts_with(|c: char| c.is_ascii_digit())
+                    {
+                        continue;
+                    }
+                    dated.push((rest.to_owned(), dir.join(&file_name)));
+                }
+            }
+        }
+
+        match self {
+            RotationScheme::Numeric => {
+                numeric.sort_by_key(|(number, _)| std::cmp::Reverse(*number));
+                numeric.into_iter().map(|(_, path)| path).collect()
+            }
+            RotationScheme::Date => {
+                dated.sort();
+                dated.into_iter().map(|(_, path)| path).collect()
+            }
+        }
+    }
+}
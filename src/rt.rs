@@ -0,0 +1,88 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::{
+    task::JoinHandle,
+    time::{sleep, Instant},
+};
+
+use crate::{restart::Backoff, LogWatcherController, LogWatcherError};
+
+/// Handle to a loop spawned with [`LogWatcherController::start`].
+///
+/// Awaiting it yields the error that ended the loop. Dropping it detaches the task,
+/// like a tokio `JoinHandle`.
+#[derive(Debug)]
+pub struct LogWatcherHandle {
+    task: JoinHandle<Result<(), LogWatcherError>>,
+}
+
+impl LogWatcherHandle {
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Future for LogWatcherHandle {
+    type Output = Result<(), LogWatcherError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.task)
+            .poll(cx)
+            .map(|result| match result {
+                Ok(result) => result,
+                Err(err) if err.is_cancelled() => Err(LogWatcherError::Aborted),
+                Err(_) => Err(LogWatcherError::Panicked),
+            })
+    }
+}
+
+impl LogWatcherController {
+    /// Spawns the loop onto the current tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Outside of a tokio runtime, or if the loop is already running.
+    pub fn start(&self) -> LogWatcherHandle {
+        LogWatcherHandle {
+            task: tokio::task::spawn(self.spawn()),
+        }
+    }
+
+    /// Like [`LogWatcherController::start`], but respawns the loop after each error,
+    /// waiting `backoff` in between. A restarted loop resumes the file where the failed
+    /// one left it, or reads it from the start if it was rotated. The backoff starts
+    /// over once a loop ran for longer than its maximum delay. The handle only resolves
+    /// once the loop exits cleanly.
+    pub fn start_with_restart(&self, backoff: Backoff) -> LogWatcherHandle {
+        let config = self.config.clone();
+        let first = self.spawn();
+
+        LogWatcherHandle {
+            task: tokio::task::spawn(async move {
+                let mut started = Instant::now();
+                let mut result = first.await;
+                let mut attempt = 0;
+
+                while result.is_err() {
+                    if started.elapsed() > backoff.max() {
+                        attempt = 0;
+                    }
+                    sleep(backoff.delay(attempt)).await;
+                    attempt = attempt.saturating_add(1);
+                    started = Instant::now();
+                    result = config.spawn(true).await;
+                }
+
+                result
+            }),
+        }
+    }
+}
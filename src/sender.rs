@@ -0,0 +1,150 @@
+use std::{path::PathBuf, time::Duration};
+
+use tokio::sync::mpsc::{error::TrySendError, Sender};
+
+#[cfg(feature = "redact")]
+use crate::redact::Redacted;
+use crate::{
+    broadcast::{Broadcast, LogSubscriber},
+    filter::Filtered,
+    limit::Limited,
+};
+
+/// Data read from one of the files of a multiplexed watcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedMessage {
+    pub path: PathBuf,
+    pub data: Vec<u8>,
+}
+
+/// Where the spawned loop delivers data: the single `LogReceiver` channel, or a
+/// broadcast shared by every subscriber.
+#[derive(Debug)]
+pub(crate) enum LogSender {
+    Channel(Sender<Vec<u8>>),
+    Broadcast(Broadcast),
+    /// One file of a multiplexed watcher, tagged with its path.
+    Tagged(PathBuf, Sender<TaggedMessage>),
+    /// Any of the above, behind a `LineFilter`.
+    Filtered(Box<Filtered>),
+    /// Any of the above, behind a `RateLimit`.
+    Limited(Box<Limited>),
+    /// Any of the above, behind a `Redactor`.
+    #[cfg(feature = "redact")]
+    Redacted(Box<Redacted>),
+}
+
+impl LogSender {
+    pub(crate) fn try_send(&self, data: Vec<u8>) -> Result<(), TrySendError<Vec<u8>>> {
+        match self {
+            LogSender::Channel(sender) => sender.try_send(data),
+            LogSender::Broadcast(broadcast) => {
+                broadcast.send(data);
+                Ok(())
+            }
+            LogSender::Tagged(path, sender) => sender
+                .try_send(TaggedMessage {
+                    path: path.clone(),
+                    data,
+                })
+                .map_err(|err| match err {
+                    TrySendError::Full(message) => TrySendError::Full(message.data),
+                    TrySendError::Closed(message) => TrySendError::Closed(message.data),
+                }),
+            LogSender::Filtered(filtered) => filtered.send(data),
+            LogSender::Limited(limited) => limited.send(data),
+            #[cfg(feature = "redact")]
+            LogSender::Redacted(redacted) => redacted.send(data),
+        }
+    }
+
+    /// Resolves once nobody can receive data anymore. Subscribers come and go, so a
+    /// broadcast is only ever closed by shutting the watcher down.
+    pub(crate) async fn closed(&self) {
+        match self {
+            LogSender::Channel(sender) => sender.closed().await,
+            LogSender::Broadcast(_) => std::future::pending().await,
+            LogSender::Tagged(_, sender) => sender.closed().await,
+            LogSender::Filtered(filtered) => Box::pin(filtered.sender.closed()).await,
+            LogSender::Limited(limited) => Box::pin(limited.sender.closed()).await,
+            #[cfg(feature = "redact")]
+            LogSender::Redacted(redacted) => Box::pin(redacted.sender.closed()).await,
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        match self {
+            LogSender::Channel(sender) => sender.is_closed(),
+            LogSender::Broadcast(_) => false,
+            LogSender::Tagged(_, sender) => sender.is_closed(),
+            LogSender::Filtered(filtered) => filtered.sender.is_closed(),
+            LogSender::Limited(limited) => limited.sender.is_closed(),
+            #[cfg(feature = "redact")]
+            LogSender::Redacted(redacted) => redacted.sender.is_closed(),
+        }
+    }
+
+    /// Resolves once the channel has room for another message, or is closed.
+    pub(crate) async fn ready(&self) {
+        match self {
+            LogSender::Channel(sender) => drop(sender.reserve().await),
+            LogSender::Broadcast(_) => {}
+            LogSender::Tagged(_, sender) => drop(sender.reserve().await),
+            LogSender::Filtered(filtered) => Box::pin(filtered.sender.ready()).await,
+            LogSender::Limited(limited) => Box::pin(limited.sender.ready()).await,
+            #[cfg(feature = "redact")]
+            LogSender::Redacted(redacted) => Box::pin(redacted.sender.ready()).await,
+        }
+    }
+
+    /// How long the loop should wait before reading more, to respect a blocking rate
+    /// limit.
+    pub(crate) fn throttle(&self) -> Option<Duration> {
+        match self {
+            LogSender::Filtered(filtered) => filtered.sender.throttle(),
+            LogSender::Limited(limited) => limited.throttle(),
+            _ => None,
+        }
+    }
+
+    /// Sends the periodic report of a rate limit, when one is due.
+    pub(crate) fn report(&self) {
+        match self {
+            LogSender::Filtered(filtered) => filtered.sender.report(),
+            LogSender::Limited(limited) => limited.report(),
+            _ => {}
+        }
+    }
+
+    /// Sends what a decorator holds back, once the loop stops reading.
+    pub(crate) fn flush(&self) {
+        match self {
+            LogSender::Filtered(filtered) => filtered.sender.flush(),
+            LogSender::Limited(limited) => limited.sender.flush(),
+            #[cfg(feature = "redact")]
+            LogSender::Redacted(redacted) => redacted.flush(),
+            _ => {}
+        }
+    }
+
+    /// `None` unless the data goes through a broadcast.
+    pub(crate) fn subscribe(&self, with_replay: bool) -> Option<LogSubscriber> {
+        match self {
+            LogSender::Broadcast(broadcast) => Some(broadcast.subscribe(with_replay)),
+            LogSender::Filtered(filtered) => filtered.sender.subscribe(with_replay),
+            LogSender::Limited(limited) => limited.sender.subscribe(with_replay),
+            #[cfg(feature = "redact")]
+            LogSender::Redacted(redacted) => redacted.sender.subscribe(with_replay),
+            _ => None,
+        }
+    }
+}
+
+/// `err` carrying `data` instead of what a decorator made of it, so the caller can send
+/// it again as it was read.
+pub(crate) fn rejected(err: TrySendError<Vec<u8>>, data: Vec<u8>) -> TrySendError<Vec<u8>> {
+    match err {
+        TrySendError::Full(_) => TrySendError::Full(data),
+        TrySendError::Closed(_) => TrySendError::Closed(data),
+    }
+}
@@ -0,0 +1,90 @@
+use std::{fmt, future::Future, pin::Pin};
+
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncSeekExt},
+};
+
+/// Future returned by the hooks of a [`LogSource`].
+pub type SourceFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, std::io::Error>> + Send + Sync + 'a>>;
+
+/// Anything a [`crate::LogWatcher`] can read from besides a path, such as an in-memory
+/// buffer, a socket or the output of a process. Built with
+/// [`crate::LogWatcher::from_source`].
+///
+/// The bytes go through the same [`crate::LogReaderMode`] framing and output channel as
+/// a file. Data is framed as it arrives, a source is never read up to its end first.
+/// Every hook has a default, so a plain reader only needs an empty `impl`.
+pub trait LogSource: AsyncRead + Send + Sync + Unpin {
+    /// Whether running out of data ends the source for good, after which the watcher
+    /// reports [`crate::LogWatcherState::Eof`]. Otherwise the source is polled again.
+    /// Defaults to `true`.
+    fn is_finite(&self) -> bool {
+        true
+    }
+
+    /// Skips the data available now, for [`crate::LogWatcherBuilder::skip_to_end`].
+    /// Does nothing by default.
+    fn skip_to_end(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Identifies what the source currently reads, such as an inode. It is checked when
+    /// no data is available and a change is handled like a rotation: the source is
+    /// reopened. Defaults to `None`, which never changes.
+    fn identity(&mut self) -> SourceFuture<'_, Option<u64>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Starts reading again, on rotation and on [`crate::LogWatcherSignal::Reload`].
+    /// Does nothing by default.
+    fn reopen(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// An already open file, followed as it grows and never reopened.
+impl LogSource for File {
+    fn is_finite(&self) -> bool {
+        false
+    }
+
+    fn skip_to_end(&mut self) -> SourceFuture<'_, ()> {
+        Box::pin(async move {
+            self.seek(std::io::SeekFrom::End(0)).await?;
+            Ok(())
+        })
+    }
+}
+
+/// A [`LogSource`] with the identity it was last seen with, unknown until first checked.
+pub(crate) struct BoxedSource {
+    pub(crate) source: Box<dyn LogSource>,
+    identity: Option<Option<u64>>,
+}
+
+impl BoxedSource {
+    pub(crate) fn new(source: Box<dyn LogSource>) -> Self {
+        Self {
+            source,
+            identity: None,
+        }
+    }
+
+    /// Whether the identity changed since the last call.
+    pub(crate) async fn rotated(&mut self) -> Result<bool, std::io::Error> {
+        let identity = self.source.identity().await?;
+        let rotated = self.identity.is_some_and(|last| last != identity);
+        self.identity = Some(identity);
+        Ok(rotated)
+    }
+}
+
+impl fmt::Debug for BoxedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedSource")
+            .field("identity", &self.identity)
+            .finish_non_exhaustive()
+    }
+}
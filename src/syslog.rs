@@ -0,0 +1,538 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use std::{borrow::Cow, time::SystemTime};
+
+use crate::records::{Fields, ParseFailure, RecordFields, RecordParser, RecordTime};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Severity of a syslog message, from its priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Informational = 6,
+    Debug = 7,
+}
+
+/// Facility of a syslog message, from its priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Ntp = 12,
+    Audit = 13,
+    Alert = 14,
+    Clock = 15,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+impl Severity {
+    const ALL: [Severity; 8] = [
+        Severity::Emergency,
+        Severity::Alert,
+        Severity::Critical,
+        Severity::Error,
+        Severity::Warning,
+        Severity::Notice,
+        Severity::Informational,
+        Severity::Debug,
+    ];
+}
+
+impl Facility {
+    const ALL: [Facility; 24] = [
+        Facility::Kern,
+        Facility::User,
+        Facility::Mail,
+        Facility::Daemon,
+        Facility::Auth,
+        Facility::Syslog,
+        Facility::Lpr,
+        Facility::News,
+        Facility::Uucp,
+        Facility::Cron,
+        Facility::AuthPriv,
+        Facility::Ftp,
+        Facility::Ntp,
+        Facility::Audit,
+        Facility::Alert,
+        Facility::Clock,
+        Facility::Local0,
+        Facility::Local1,
+        Facility::Local2,
+        Facility::Local3,
+        Facility::Local4,
+        Facility::Local5,
+        Facility::Local6,
+        Facility::Local7,
+    ];
+}
+
+/// An `[id name="value" ...]` element of an RFC 5424 message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredData {
+    pub id: String,
+    pub params: Fields,
+}
+
+/// A syslog message, as parsed by [`Rfc3164`] or [`Rfc5424`]. Fields missing from the
+/// line, or logged as `-` in RFC 5424, are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogMessage {
+    /// Only `None` for RFC 3164 lines without a priority, as written by syslog daemons
+    /// to local files.
+    pub facility: Option<Facility>,
+    pub severity: Option<Severity>,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    pub hostname: Option<String>,
+    /// The tag of RFC 3164 lines.
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    /// Only in RFC 5424.
+    pub msg_id: Option<String>,
+    /// Only in RFC 5424.
+    pub structured_data: Vec<StructuredData>,
+    pub message: String,
+}
+
+impl RecordTime for SyslogMessage {
+    fn record_time(&self) -> Option<SystemTime> {
+        self.timestamp.map(Into::into)
+    }
+}
+
+/// Fields are named after the struct members, with the timestamp as RFC 3339 and the
+/// facility and severity as their codes.
+impl RecordFields for SyslogMessage {
+    fn field(&self, name: &str) -> Option<Cow<'_, str>> {
+        match name {
+            "facility" => self
+                .facility
+                .map(|facility| Cow::Owned((facility as u8).to_string())),
+            "severity" => self
+                .severity
+                .map(|severity| Cow::Owned((severity as u8).to_string())),
+            "timestamp" => self
+                .timestamp
+                .map(|timestamp| Cow::Owned(timestamp.to_rfc3339())),
+            "hostname" => self.hostname.as_deref().map(Cow::Borrowed),
+            "app_name" => self.app_name.as_deref().map(Cow::Borrowed),
+            "proc_id" => self.proc_id.as_deref().map(Cow::Borrowed),
+            "msg_id" => self.msg_id.as_deref().map(Cow::Borrowed),
+            "message" => Some(Cow::Borrowed(&self.message)),
+            _ => None,
+        }
+    }
+}
+
+/// Parses BSD syslog lines such as `<34>Oct 11 22:14:15 host sshd[42]: message`.
+///
+/// The priority is optional. The timestamp has neither year nor timezone: it is read in
+/// the configured offset, UTC by default, and given the year that puts it closest to
+/// now without being more than a day ahead. An RFC 3339 timestamp is accepted instead,
+/// as written by syslog daemons configured for high precision.
+#[derive(Debug, Clone, Copy)]
+pub struct Rfc3164 {
+    offset: FixedOffset,
+}
+
+impl Rfc3164 {
+    pub fn new() -> Self {
+        Self {
+            offset: FixedOffset::east_opt(0).unwrap(),
+        }
+    }
+
+    pub fn offset(self, offset: FixedOffset) -> Self {
+        Self { offset }
+    }
+}
+
+impl Default for Rfc3164 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses syslog protocol lines such as
+/// `<165>1 2003-10-11T22:14:15.003Z host app 42 ID47 [id a="1"] message`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rfc5424;
+
+impl RecordParser for Rfc3164 {
+    type Record = SyslogMessage;
+
+    fn parse(&self, line: &[u8]) -> Result<SyslogMessage, ParseFailure> {
+        self.parse_at(line, SystemTime::now())
+    }
+
+    /// A timestamp without a year gets the one closest to `read_at`.
+    fn parse_at(&self, line: &[u8], read_at: SystemTime) -> Result<SyslogMessage, ParseFailure> {
+        let line = std::str::from_utf8(line)?;
+        let (priority, rest) = match line.starts_with('<') {
+            true => {
+                let (priority, rest) = priority(line)?;
+                (Some(priority), rest)
+            }
+            false => (None, line),
+        };
+
+        let (timestamp, rest) = match rest.split_once(' ') {
+            Some((timestamp, _)) if timestamp.starts_with(|c: char| c.is_ascii_digit()) => (
+                DateTime::parse_from_rfc3339(timestamp)?,
+                &rest[timestamp.len()..],
+            ),
+            _ => {
+                let timestamp = rest.get(..15).ok_or("missing timestamp")?;
+                let now = DateTime::<Utc>::from(read_at)
+                    .with_timezone(&self.offset)
+                    .naive_local();
+                let timestamp = bsd_timestamp(timestamp, now)?
+                    .and_local_timezone(self.offset)
+                    .single()
+                    .ok_or("invalid timestamp")?;
+                (timestamp, &rest[15..])
+            }
+        };
+
+        let (hostname, rest) = rest
+            .strip_prefix(' ')
+            .and_then(|rest| rest.split_once(' '))
+            .filter(|(hostname, _)| !hostname.is_empty())
+            .ok_or("missing hostname")?;
+
+        // A tag is a word ending in `:`, optionally with a `[pid]` before the colon.
+        let (app_name, proc_id, message) = match rest.split_once(':') {
+            Some((tag, message)) if !tag.is_empty() && !tag.contains(' ') => {
+                let message = message.strip_prefix(' ').unwrap_or(message);
+                match tag.strip_suffix(']').and_then(|tag| tag.split_once('[')) {
+                    Some((app_name, proc_id)) => (Some(app_name), Some(proc_id), message),
+                    None => (Some(tag), None, message),
+                }
+            }
+            _ => (None, None, rest),
+        };
+
+        Ok(SyslogMessage {
+            facility: priority.map(|(facility, _)| facility),
+            severity: priority.map(|(_, severity)| severity),
+            timestamp: Some(timestamp),
+            hostname: Some(hostname.to_owned()),
+            app_name: app_name.map(str::to_owned),
+            proc_id: proc_id.map(str::to_owned),
+            msg_id: None,
+            structured_data: Vec::new(),
+            message: message.to_owned(),
+        })
+    }
+}
+
+impl RecordParser for Rfc5424 {
+    type Record = SyslogMessage;
+
+    fn parse(&self, line: &[u8]) -> Result<SyslogMessage, ParseFailure> {
+        let line = std::str::from_utf8(line)?;
+        let ((facility, severity), rest) = priority(line)?;
+
+        let mut header = rest.splitn(7, ' ');
+        let mut field = |name: &str| header.next().ok_or(format!("missing {}", name));
+        if field("version")? != "1" {
+            return Err("unsupported version".into());
+        }
+        let timestamp = match field("timestamp")? {
+            "-" => None,
+            timestamp => Some(DateTime::parse_from_rfc3339(timestamp)?),
+        };
+        let hostname = nil(field("hostname")?);
+        let app_name = nil(field("app name")?);
+        let proc_id = nil(field("proc id")?);
+        let msg_id = nil(field("message id")?);
+        let rest = field("structured data")?;
+
+        let (structured_data, rest) = structured_data(rest)?;
+        let message = match rest {
+            "" => "",
+            rest => rest
+                .strip_prefix(' ')
+                .ok_or("missing space before message")?,
+        };
+
+        Ok(SyslogMessage {
+            facility: Some(facility),
+            severity: Some(severity),
+            timestamp,
+            hostname,
+            app_name,
+            proc_id,
+            msg_id,
+            structured_data,
+            message: message
+                .strip_prefix('\u{feff}')
+                .unwrap_or(message)
+                .to_owned(),
+        })
+    }
+}
+
+/// Splits the `<PRI>` off `line`.
+fn priority(line: &str) -> Result<((Facility, Severity), &str), ParseFailure> {
+    let (priority, rest) = line
+        .strip_prefix('<')
+        .and_then(|rest| rest.split_once('>'))
+        .ok_or("missing priority")?;
+    let priority: usize = match priority.len() {
+        1..=3 => priority.parse()?,
+        _ => return Err("invalid priority".into()),
+    };
+    let facility = *Facility::ALL.get(priority / 8).ok_or("invalid priority")?;
+
+    Ok(((facility, Severity::ALL[priority % 8]), rest))
+}
+
+fn nil(value: &str) -> Option<String> {
+    (value != "-").then(|| value.to_owned())
+}
+
+/// Reads a `Mmm dd hh:mm:ss` timestamp in the year that puts it at most a day after
+/// `now`, both being wall clock times of the same timezone.
+pub(crate) fn bsd_timestamp(
+    timestamp: &str,
+    now: NaiveDateTime,
+) -> Result<NaiveDateTime, ParseFailure> {
+    let month = MONTHS
+        .iter()
+        .position(|month| timestamp.starts_with(month))
+        .ok_or("invalid month")? as u32
+        + 1;
+    let day: u32 = timestamp
+        .get(4..6)
+        .ok_or("missing day")?
+        .trim_start()
+        .parse()?;
+    let time = NaiveTime::parse_from_str(timestamp.get(7..).ok_or("missing time")?, "%H:%M:%S")?;
+
+    // Going back a few years also finds the last leap year for February 29th.
+    for year in (now.year() - 4..=now.year() + 1).rev() {
+        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+            let candidate = date.and_time(time);
+            if candidate <= now + Duration::days(1) {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    Err("invalid date".into())
+}
+
+/// Splits the structured data elements, or the `-` standing for none, off `rest`.
+fn structured_data(rest: &str) -> Result<(Vec<StructuredData>, &str), ParseFailure> {
+    if let Some(rest) = rest.strip_prefix('-') {
+        return Ok((Vec::new(), rest));
+    }
+
+    let mut elements = Vec::new();
+    let mut rest = rest;
+
+    while let Some(element) = rest.strip_prefix('[') {
+        let id_end = element
+            .find([' ', ']'])
+            .ok_or("unterminated structured data")?;
+        let id = &element[..id_end];
+        if id.is_empty() {
+            return Err("missing structured data id".into());
+        }
+        rest = &element[id_end..];
+
+        let mut params = Fields::default();
+        while let Some(param) = rest.strip_prefix(' ') {
+            let (name, value) = param
+                .split_once("=\"")
+                .ok_or("invalid structured data parameter")?;
+
+            // Values escape `"`, `\` and `]` with a backslash.
+            let mut unescaped = String::new();
+            let mut chars = value.char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((index, '"')) => break index,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c @ ('"' | '\\' | ']'))) => unescaped.push(c),
+                        Some((_, c)) => {
+                            unescaped.push('\\');
+                            unescaped.push(c);
+                        }
+                        None => return Err("unterminated structured data".into()),
+                    },
+                    Some((_, c)) => unescaped.push(c),
+                    None => return Err("unterminated structured data".into()),
+                }
+            };
+
+            params.push(name, unescaped);
+            rest = &value[end + 1..];
+        }
+
+        rest = rest
+            .strip_prefix(']')
+            .ok_or("unterminated structured data")?;
+        elements.push(StructuredData {
+            id: id.to_owned(),
+            params,
+        });
+    }
+
+    if elements.is_empty() {
+        return Err("missing structured data".into());
+    }
+    Ok((elements, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_at(timestamp: &str) -> SystemTime {
+        DateTime::parse_from_rfc3339(timestamp).unwrap().into()
+    }
+
+    #[test]
+    fn rfc3164() {
+        let line = b"<34>Oct 11 22:14:15 mymachine su[42]: 'su root' failed";
+        let message = Rfc3164::new()
+            .parse_at(line, read_at("2003-10-12T00:00:00Z"))
+            .unwrap();
+        assert_eq!(message.facility, Some(Facility::Auth));
+        assert_eq!(message.severity, Some(Severity::Critical));
+        assert_eq!(
+            message.timestamp.unwrap().to_rfc3339(),
+            "2003-10-11T22:14:15+00:00"
+        );
+        assert_eq!(message.hostname.as_deref(), Some("mymachine"));
+        assert_eq!(message.app_name.as_deref(), Some("su"));
+        assert_eq!(message.proc_id.as_deref(), Some("42"));
+        assert_eq!(message.message, "'su root' failed");
+
+        // As written to local files, without a priority or a tag.
+        let message = Rfc3164::new()
+            .parse(b"2003-10-11T22:14:15.003Z host just a message")
+            .unwrap();
+        assert_eq!(message.facility, None);
+        assert_eq!(message.app_name, None);
+        assert_eq!(message.message, "just a message");
+    }
+
+    #[test]
+    fn rfc3164_year_rollover() {
+        let parser = Rfc3164::new();
+        let year = |line: &[u8], read: &str| {
+            let message = parser.parse_at(line, read_at(read)).unwrap();
+            message.timestamp.unwrap().year()
+        };
+
+        // Read just after new year, the last lines of December are from the year before.
+        assert_eq!(
+            year(b"Dec 31 23:59:59 host app: a", "2025-01-01T00:00:10Z"),
+            2024
+        );
+        assert_eq!(
+            year(b"Jan  1 00:00:05 host app: a", "2025-01-01T00:00:10Z"),
+            2025
+        );
+        // A clock a little ahead of the reader still stays in the current year.
+        assert_eq!(
+            year(b"Jan  1 00:00:05 host app: a", "2024-12-31T23:59:59Z"),
+            2025
+        );
+        // February 29th goes back to the last leap year.
+        assert_eq!(
+            year(b"Feb 29 12:00:00 host app: a", "2025-06-01T00:00:00Z"),
+            2024
+        );
+
+        let offset = FixedOffset::east_opt(3600).unwrap();
+        let message = Rfc3164::new()
+            .offset(offset)
+            .parse_at(
+                b"Jan  1 00:30:00 host app: a",
+                read_at("2024-12-31T23:45:00Z"),
+            )
+            .unwrap();
+        assert_eq!(
+            message.timestamp.unwrap().to_rfc3339(),
+            "2025-01-01T00:30:00+01:00"
+        );
+    }
+
+    #[test]
+    fn rfc3164_malformed() {
+        for line in [
+            &b"<34>Oct 11 22:14"[..],
+            b"<34>Oct 11 22:14:15",
+            b"<34>Foo 11 22:14:15 host app: a",
+            b"<34>Oct 32 22:14:15 host app: a",
+            b"<192>Oct 11 22:14:15 host app: a",
+            b"<34 Oct 11 22:14:15 host app: a",
+        ] {
+            assert!(Rfc3164::new().parse(line).is_err(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn rfc5424() {
+        let line = br#"<165>1 2003-10-11T22:14:15.003Z host app 42 ID47 [id a="1" b="x\"y\]"][other] message"#;
+        let message = Rfc5424.parse(line).unwrap();
+        assert_eq!(message.facility, Some(Facility::Local4));
+        assert_eq!(message.severity, Some(Severity::Notice));
+        assert_eq!(message.msg_id.as_deref(), Some("ID47"));
+        assert_eq!(message.structured_data.len(), 2);
+        assert_eq!(message.structured_data[0].id, "id");
+        assert_eq!(message.structured_data[0].params.get("b"), Some(r#"x"y]"#));
+        assert!(message.structured_data[1].params.is_empty());
+        assert_eq!(message.message, "message");
+
+        let message = Rfc5424.parse(b"<13>1 - - - - - -").unwrap();
+        assert_eq!(message.timestamp, None);
+        assert_eq!(message.hostname, None);
+        assert!(message.structured_data.is_empty());
+        assert_eq!(message.message, "");
+    }
+
+    #[test]
+    fn rfc5424_malformed() {
+        for line in [
+            &b"<13>2 - - - - - -"[..],
+            b"<13>1 - - -",
+            b"<13>1 yesterday - - - - -",
+            br#"<13>1 - - - - - [id a="1"#,
+            br#"<13>1 - - - - - [id a="1\"#,
+            b"<13>1 - - - - - [] message",
+            b"<13>1 - - - - - -message",
+        ] {
+            assert!(Rfc5424.parse(line).is_err(), "{:?}", line);
+        }
+    }
+}
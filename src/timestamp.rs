@@ -0,0 +1,336 @@
+#[cfg(feature = "regex")]
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use crate::{
+    records::{ParseFailure, RecordFields, RecordParser, RecordTime},
+    syslog::bsd_timestamp,
+};
+
+/// How a timestamp is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampFormat {
+    Rfc3339,
+    Rfc2822,
+    /// A strftime pattern such as `%Y-%m-%d %H:%M:%S%.f`, with or without an offset.
+    Strftime(String),
+    /// Seconds since the Unix epoch, possibly with a fractional part.
+    EpochSeconds,
+    EpochMillis,
+    /// The `Mmm dd hh:mm:ss` of BSD syslog, given the year that puts it at most a day
+    /// in the future.
+    Syslog,
+}
+
+/// Timezone of timestamps written without an offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timezone {
+    #[default]
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+#[derive(Debug, Clone)]
+enum TimestampSource {
+    Field(String),
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+/// Finds the event time of a record, in one of its fields or in the raw line.
+#[derive(Debug, Clone)]
+pub struct TimestampExtractor {
+    source: TimestampSource,
+    format: TimestampFormat,
+    timezone: Timezone,
+}
+
+impl TimestampExtractor {
+    /// Reads the timestamp from a field of the parsed record.
+    pub fn field(name: impl Into<String>, format: TimestampFormat) -> Self {
+        Self {
+            source: TimestampSource::Field(name.into()),
+            format,
+            timezone: Timezone::Utc,
+        }
+    }
+
+    /// Reads the timestamp from the raw line: the `timestamp` group of `regex` if it has
+    /// one, otherwise its first group or the whole match.
+    #[cfg(feature = "regex")]
+    pub fn regex(regex: Regex, format: TimestampFormat) -> Self {
+        Self {
+            source: TimestampSource::Regex(regex),
+            format,
+            timezone: Timezone::Utc,
+        }
+    }
+
+    /// Used for timestamps without an offset and for the read time. Defaults to UTC.
+    pub fn timezone(self, timezone: Timezone) -> Self {
+        Self { timezone, ..self }
+    }
+
+    /// `read_at` stands in for now, for formats without a year.
+    #[cfg_attr(not(feature = "regex"), allow(unused_variables))]
+    fn extract(
+        &self,
+        line: &[u8],
+        record: &impl RecordFields,
+        read_at: DateTime<FixedOffset>,
+    ) -> Option<DateTime<FixedOffset>> {
+        let text = match &self.source {
+            TimestampSource::Field(name) => record.field(name)?,
+            #[cfg(feature = "regex")]
+            TimestampSource::Regex(regex) => {
+                let captures = regex.captures(std::str::from_utf8(line).ok()?)?;
+                let found = captures
+                    .name("timestamp")
+                    .or_else(|| captures.get(1))
+                    .or_else(|| captures.get(0))?;
+                Cow::Borrowed(found.as_str())
+            }
+        };
+        self.parse(text.trim(), read_at).ok()
+    }
+
+    fn parse(
+        &self,
+        text: &str,
+        read_at: DateTime<FixedOffset>,
+    ) -> Result<DateTime<FixedOffset>, ParseFailure> {
+        Ok(match &self.format {
+            TimestampFormat::Rfc3339 => DateTime::parse_from_rfc3339(text)?,
+            TimestampFormat::Rfc2822 => DateTime::parse_from_rfc2822(text)?,
+            TimestampFormat::Strftime(format) => match DateTime::parse_from_str(text, format) {
+                Ok(timestamp) => timestamp,
+                Err(_) => self.localize(NaiveDateTime::parse_from_str(text, format)?)?,
+            },
+            TimestampFormat::EpochSeconds => {
+                let seconds: f64 = text.parse()?;
+                // The fraction counts forward from the second below, also before 1970.
+                let whole = seconds.floor();
+                let nanos = ((seconds - whole) * 1e9).round().min(999_999_999.0) as u32;
+                let timestamp = DateTime::from_timestamp(whole as i64, nanos)
+                    .ok_or("timestamp out of range")?;
+                self.convert(timestamp)
+            }
+            TimestampFormat::EpochMillis => {
+                let timestamp = DateTime::from_timestamp_millis(text.parse()?)
+                    .ok_or("timestamp out of range")?;
+                self.convert(timestamp)
+            }
+            TimestampFormat::Syslog => {
+                self.localize(bsd_timestamp(text, read_at.naive_local())?)?
+            }
+        })
+    }
+
+    fn localize(&self, timestamp: NaiveDateTime) -> Result<DateTime<FixedOffset>, ParseFailure> {
+        let localized = match self.timezone {
+            Timezone::Utc => Some(timestamp.and_utc().fixed_offset()),
+            // Ambiguous times at the end of daylight saving time take the first one.
+            Timezone::Local => Local
+                .from_local_datetime(&timestamp)
+                .earliest()
+                .map(|timestamp| timestamp.fixed_offset()),
+            Timezone::Fixed(offset) => offset.from_local_datetime(&timestamp).single(),
+        };
+
+        localized.ok_or_else(|| "timestamp does not exist in timezone".into())
+    }
+
+    fn convert(&self, timestamp: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.timezone {
+            Timezone::Utc => timestamp.fixed_offset(),
+            Timezone::Local => timestamp.with_timezone(&Local).fixed_offset(),
+            Timezone::Fixed(offset) => timestamp.with_timezone(&offset),
+        }
+    }
+}
+
+/// What is known about a record besides its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// Event time of the record, or the time its line was read when none was found.
+    pub timestamp: DateTime<FixedOffset>,
+    /// Whether `timestamp` was found in the record.
+    pub extracted: bool,
+}
+
+/// A record with its [`Metadata`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<R> {
+    pub record: R,
+    pub metadata: Metadata,
+}
+
+/// The extracted timestamp, never the read time standing in for it.
+impl<R> RecordTime for Event<R> {
+    fn record_time(&self) -> Option<SystemTime> {
+        self.metadata
+            .extracted
+            .then(|| self.metadata.timestamp.into())
+    }
+}
+
+/// Parser attaching timestamps to the records of another, built with
+/// [`RecordParser::timestamped`].
+#[derive(Debug, Clone)]
+pub struct Timestamped<P> {
+    parser: P,
+    extractor: TimestampExtractor,
+}
+
+impl<P> Timestamped<P> {
+    pub(crate) fn new(parser: P, extractor: TimestampExtractor) -> Self {
+        Self { parser, extractor }
+    }
+}
+
+impl<P> RecordParser for Timestamped<P>
+where
+    P: RecordParser,
+    P::Record: RecordFields,
+{
+    type Record = Event<P::Record>;
+
+    fn parse(&self, line: &[u8]) -> Result<Event<P::Record>, ParseFailure> {
+        self.parse_at(line, SystemTime::now())
+    }
+
+    fn parse_at(&self, line: &[u8], read_at: SystemTime) -> Result<Event<P::Record>, ParseFailure> {
+        let record = self.parser.parse_at(line, read_at)?;
+        let read_at = self.extractor.convert(DateTime::<Utc>::from(read_at));
+        let metadata = match self.extractor.extract(line, &record, read_at) {
+            Some(timestamp) => Metadata {
+                timestamp,
+                extracted: true,
+            },
+            None => Metadata {
+                timestamp: read_at,
+                extracted: false,
+            },
+        };
+
+        Ok(Event { record, metadata })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fields;
+
+    fn extract(format: TimestampFormat, text: &str) -> Option<String> {
+        let mut fields = Fields::default();
+        fields.push("ts", text);
+        let read_at = DateTime::parse_from_rfc3339("2025-01-01T00:00:10Z").unwrap();
+        TimestampExtractor::field("ts", format)
+            .extract(b"", &fields, read_at)
+            .map(|timestamp| timestamp.to_rfc3339())
+    }
+
+    #[test]
+    fn formats() {
+        let cases = [
+            (TimestampFormat::Rfc3339, "2024-02-29T12:00:00.5+02:00"),
+            (TimestampFormat::Rfc2822, "Thu, 29 Feb 2024 12:00:00 +0200"),
+            (
+                TimestampFormat::Strftime("%d/%m/%Y %H:%M:%S%.f %z".to_owned()),
+                "29/02/2024 12:00:00.5 +0200",
+            ),
+            (TimestampFormat::EpochSeconds, "1709200800"),
+            (TimestampFormat::EpochSeconds, " 1709200800.25 "),
+            (TimestampFormat::EpochMillis, "1709200800250"),
+        ];
+        let expected = [
+            "2024-02-29T12:00:00.500+02:00",
+            "2024-02-29T12:00:00+02:00",
+            "2024-02-29T12:00:00.500+02:00",
+            "2024-02-29T10:00:00+00:00",
+            "2024-02-29T10:00:00.250+00:00",
+            "2024-02-29T10:00:00.250+00:00",
+        ];
+        for ((format, text), expected) in cases.into_iter().zip(expected) {
+            assert_eq!(extract(format, text).as_deref(), Some(expected), "{text}");
+        }
+
+        // Before the epoch the fraction still counts forward from the whole second.
+        assert_eq!(
+            extract(TimestampFormat::EpochSeconds, "-1.5").as_deref(),
+            Some("1969-12-31T23:59:58.500+00:00")
+        );
+    }
+
+    #[test]
+    fn timezones_of_timestamps_without_offset() {
+        let format = TimestampFormat::Strftime("%Y-%m-%d %H:%M:%S".to_owned());
+        assert_eq!(
+            extract(format.clone(), "2024-02-29 12:00:00").as_deref(),
+            Some("2024-02-29T12:00:00+00:00")
+        );
+
+        let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+        let extractor = TimestampExtractor::field("ts", format).timezone(Timezone::Fixed(offset));
+        let mut fields = Fields::default();
+        fields.push("ts", "2024-02-29 12:00:00");
+        let timestamp = extractor
+            .extract(b"", &fields, Utc::now().fixed_offset())
+            .unwrap();
+        assert_eq!(timestamp.to_rfc3339(), "2024-02-29T12:00:00-05:00");
+    }
+
+    #[test]
+    fn syslog_year_comes_from_the_read_time() {
+        assert_eq!(
+            extract(TimestampFormat::Syslog, "Dec 31 23:59:59").as_deref(),
+            Some("2024-12-31T23:59:59+00:00")
+        );
+        assert_eq!(
+            extract(TimestampFormat::Syslog, "Jan  1 00:00:05").as_deref(),
+            Some("2025-01-01T00:00:05+00:00")
+        );
+    }
+
+    #[test]
+    fn invalid_timestamps_are_not_extracted() {
+        assert_eq!(
+            extract(TimestampFormat::Rfc3339, "2024-02-30T12:00:00Z"),
+            None
+        );
+        assert_eq!(extract(TimestampFormat::Rfc3339, "2024-02-29T12:00"), None);
+        assert_eq!(extract(TimestampFormat::EpochMillis, "1e12"), None);
+        assert_eq!(
+            extract(TimestampFormat::EpochSeconds, "99999999999999999999"),
+            None
+        );
+        assert_eq!(extract(TimestampFormat::Syslog, "Foo  1 00:00:05"), None);
+    }
+
+    #[test]
+    fn read_time_stands_in() {
+        let parser =
+            crate::Logfmt.timestamped(TimestampExtractor::field("ts", TimestampFormat::Rfc3339));
+        let read_at = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86_400);
+
+        let event = parser.parse_at(b"msg=a ts=bad", read_at).unwrap();
+        assert!(!event.metadata.extracted);
+        assert_eq!(
+            event.metadata.timestamp.to_rfc3339(),
+            "1970-01-02T00:00:00+00:00"
+        );
+
+        let event = parser
+            .parse_at(b"msg=a ts=2024-02-29T12:00:00Z", read_at)
+            .unwrap();
+        assert!(event.metadata.extracted);
+        assert_eq!(event.record.get("msg"), Some("a"));
+    }
+}